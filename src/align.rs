@@ -0,0 +1,262 @@
+//! Aligns the spacers of two related arrays to reconstruct their acquisition history.
+
+use crate::sequence::hamming_distance;
+use crate::{Array, Repeat, Strand};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// What a column of a [`SpacerAlignment`] says about the history of the two arrays.
+pub enum SpacerAlignmentKind {
+    /// The spacer is in both arrays, so it was most likely in their common ancestor.
+    Shared {
+        /// The number of mismatches between the two copies of the spacer.
+        mismatches: usize,
+    },
+    /// The spacer is only in one array, and is closer to the leader than any shared spacer, so it
+    /// was most likely acquired after the strains diverged.
+    LeaderInsertion,
+    /// The spacer is only in one array, between two shared spacers, so it was most likely
+    /// deleted from the other array.
+    InternalDeletion,
+    /// The spacer is only in one array, and is closer to the trailer than any shared spacer.
+    TrailerEnd,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// One column of a [`SpacerAlignment`].
+pub struct AlignedSpacer {
+    /// The index of the spacer's unit in the first array's
+    /// [`repeat_spacers`](Array::repeat_spacers), or `None` if the column is a gap in that array.
+    pub a: Option<usize>,
+    /// The index of the spacer's unit in the second array's
+    /// [`repeat_spacers`](Array::repeat_spacers), or `None` if the column is a gap in that array.
+    pub b: Option<usize>,
+    /// What the column says about the history of the two arrays.
+    pub kind: SpacerAlignmentKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// An alignment of the spacers of two arrays, where each spacer is treated as a single symbol.
+/// Columns run from the leader end to the trailer end.
+pub struct SpacerAlignment {
+    /// The columns of the alignment.
+    pub columns: Vec<AlignedSpacer>,
+}
+
+impl SpacerAlignment {
+    /// The number of spacers found in both arrays.
+    pub fn shared_count(&self) -> usize {
+        self.columns
+            .iter()
+            .filter(|c| matches!(c.kind, SpacerAlignmentKind::Shared { .. }))
+            .count()
+    }
+}
+
+/// Aligns the spacers of two arrays from closely related strains, so that as many spacers as
+/// possible line up without reordering either array. Two spacers line up if they differ by at
+/// most `max_mismatches` substitutions.
+///
+/// Spacers are acquired at the leader end, which is taken to be the start of the array unless
+/// its [`strand`](Array::strand) is [`Strand::Reverse`].
+pub fn align_spacers(a: &Array, b: &Array, max_mismatches: usize) -> SpacerAlignment {
+    let a_spacers = spacers_from_leader(a);
+    let b_spacers = spacers_from_leader(b);
+    let (n, m) = (a_spacers.len(), b_spacers.len());
+
+    // The longest common subsequence of the two lists of spacers.
+    let mut lengths = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = if hamming_distance(a_spacers[i].1, b_spacers[j].1) <= max_mismatches {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut columns = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m {
+            let mismatches = hamming_distance(a_spacers[i].1, b_spacers[j].1);
+            if mismatches <= max_mismatches && lengths[i][j] == lengths[i + 1][j + 1] + 1 {
+                columns.push(column(
+                    Some(a_spacers[i].0),
+                    Some(b_spacers[j].0),
+                    mismatches,
+                ));
+                i += 1;
+                j += 1;
+                continue;
+            }
+        }
+        if j == m || (i < n && lengths[i + 1][j] >= lengths[i][j + 1]) {
+            columns.push(column(Some(a_spacers[i].0), None, 0));
+            i += 1;
+        } else {
+            columns.push(column(None, Some(b_spacers[j].0), 0));
+            j += 1;
+        }
+    }
+
+    // Classify the gaps by where they are relative to the shared spacers.
+    let is_shared = |c: &AlignedSpacer| matches!(c.kind, SpacerAlignmentKind::Shared { .. });
+    let first_shared = columns.iter().position(is_shared);
+    let last_shared = columns.iter().rposition(is_shared);
+    for (position, column) in columns.iter_mut().enumerate() {
+        if is_shared(column) {
+            continue;
+        }
+        column.kind = match (first_shared, last_shared) {
+            (Some(first), Some(last)) if position > first && position < last => {
+                SpacerAlignmentKind::InternalDeletion
+            }
+            (Some(first), _) if position > first => SpacerAlignmentKind::TrailerEnd,
+            _ => SpacerAlignmentKind::LeaderInsertion,
+        };
+    }
+    SpacerAlignment { columns }
+}
+
+/// A column of the alignment, classified as shared if both arrays have a spacer in it.
+fn column(a: Option<usize>, b: Option<usize>, mismatches: usize) -> AlignedSpacer {
+    let kind = if a.is_some() && b.is_some() {
+        SpacerAlignmentKind::Shared { mismatches }
+    } else {
+        SpacerAlignmentKind::LeaderInsertion
+    };
+    AlignedSpacer { a, b, kind }
+}
+
+/// The index and sequence of each spacer in an array, starting from the leader end.
+fn spacers_from_leader<'a>(array: &Array<'a>) -> Vec<(usize, &'a str)> {
+    let mut spacers: Vec<(usize, &'a str)> = array
+        .repeat_spacers
+        .iter()
+        .enumerate()
+        .filter_map(|(index, repeat)| match repeat {
+            Repeat::WithSpacer(rs) => Some((index, rs.spacer)),
+            Repeat::WithoutSpacer(_) => None,
+        })
+        .collect();
+    if array.strand == Some(Strand::Reverse) {
+        spacers.reverse();
+    }
+    spacers
+}
+
+impl fmt::Display for SpacerAlignment {
+    /// Renders the alignment as three lines: the spacers of the first array, a line of symbols
+    /// describing each column, and the spacers of the second array. Spacers are shown by their
+    /// index in `repeat_spacers`, and gaps by `-`. The symbols are `|` for shared spacers, `*` for
+    /// shared spacers with mismatches, `+` for leader-end insertions, `x` for internal deletions
+    /// and `.` for differences at the trailer end.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = |index: Option<usize>| index.map_or("-".to_string(), |i| i.to_string());
+        let width = self
+            .columns
+            .iter()
+            .flat_map(|c| [label(c.a).len(), label(c.b).len()])
+            .max()
+            .unwrap_or(1);
+        let mut lines = [String::from("A:"), String::from("  "), String::from("B:")];
+        for column in &self.columns {
+            let symbol = match column.kind {
+                SpacerAlignmentKind::Shared { mismatches: 0 } => "|",
+                SpacerAlignmentKind::Shared { .. } => "*",
+                SpacerAlignmentKind::LeaderInsertion => "+",
+                SpacerAlignmentKind::InternalDeletion => "x",
+                SpacerAlignmentKind::TrailerEnd => ".",
+            };
+            for (line, text) in
+                lines
+                    .iter_mut()
+                    .zip([label(column.a), symbol.to_string(), label(column.b)])
+            {
+                line.push_str(&format!(" {:>width$}", text, width = width));
+            }
+        }
+        writeln!(f, "{}", lines[0].trim_end())?;
+        writeln!(f, "{}", lines[1].trim_end())?;
+        write!(f, "{}", lines[2].trim_end())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_array;
+
+    /// Builds an array with one spacer per letter, each a distinct sequence.
+    fn array(spacers: &str) -> String {
+        let mut input =
+            String::from("\nCRISPR 1   Range: 1 - 100\nPOSITION\tREPEAT\tSPACER\n--------\n");
+        for (i, letter) in spacers.chars().enumerate() {
+            let spacer = match letter {
+                'a' => "AAAACCCCGG",
+                'b' => "CCCCGGGGTT",
+                'c' => "GGGGTTTTAA",
+                'd' => "TTTTAAAACC",
+                'e' => "ACACACACAC",
+                'f' => "GTGTGTGTGT",
+                'g' => "GTGTGTGTGA",
+                _ => unreachable!(),
+            };
+            input.push_str(&format!(
+                "{}\t\tGTTTCAAT\t{}\t[ 8, 10 ]\n",
+                i * 18 + 1,
+                spacer
+            ));
+        }
+        input.push_str(&format!(
+            "{}\t\tGTTTCAAT\n--------\nRepeats: 0\n",
+            spacers.len() * 18 + 1
+        ));
+        input
+    }
+
+    #[test]
+    fn test_align_spacers() {
+        let (a, b) = (array("eabcd"), array("fabd"));
+        let (_, a) = parse_array(&a).unwrap();
+        let (_, b) = parse_array(&b).unwrap();
+        let alignment = align_spacers(&a, &b, 0);
+        use SpacerAlignmentKind::*;
+        let kinds: Vec<SpacerAlignmentKind> = alignment.columns.iter().map(|c| c.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                LeaderInsertion,
+                LeaderInsertion,
+                Shared { mismatches: 0 },
+                Shared { mismatches: 0 },
+                InternalDeletion,
+                Shared { mismatches: 0 },
+            ]
+        );
+        assert_eq!(alignment.shared_count(), 3);
+        assert_eq!(
+            alignment.to_string(),
+            "A: 0 - 1 2 3 4\n   + + | | x |\nB: - 0 1 2 - 3"
+        );
+
+        // With one mismatch allowed, f and g line up.
+        let (a, b) = (array("gabc"), array("fab"));
+        let (_, a) = parse_array(&a).unwrap();
+        let (_, b) = parse_array(&b).unwrap();
+        let alignment = align_spacers(&a, &b, 1);
+        assert_eq!(alignment.to_string(), "A: 0 1 2 3\n   * | | .\nB: 0 1 2 -");
+    }
+
+    #[test]
+    fn test_align_spacers_reverse_strand() {
+        let (a, b) = (array("abc"), array("cbad"));
+        let (_, mut a) = parse_array(&a).unwrap();
+        let (_, b) = parse_array(&b).unwrap();
+        a.strand = Some(Strand::Reverse);
+        let alignment = align_spacers(&a, &b, 0);
+        assert_eq!(alignment.to_string(), "A: 2 1 0 -\n   | | | .\nB: 0 1 2 3");
+    }
+}
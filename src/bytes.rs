@@ -0,0 +1,182 @@
+//! Parsing reports that are not entirely valid UTF-8.
+
+use crate::{parse_arrays, record_ranges, split_accession_line, Array};
+use nom::{
+    bytes::complete::tag,
+    character::complete::not_line_ending,
+    error::{Error, ErrorKind},
+    sequence::pair,
+    Err, IResult,
+};
+use std::borrow::Cow;
+
+#[derive(Debug, PartialEq)]
+/// All of the CRISPR arrays in a single contig or genome, parsed from bytes.
+///
+/// This is identical to [`Contig`](crate::Contig) except that the accession is kept as raw bytes,
+/// since MinCED copies FASTA headers through verbatim and they are not always valid UTF-8.
+pub struct ByteContig<'a> {
+    /// Accession of the contig/genome.
+    pub accession: &'a [u8],
+    /// Length of the contig/genome in base pairs.
+    pub bp: usize,
+    /// The CRISPR arrays in this contig/genome.
+    pub arrays: Vec<Array<'a>>,
+}
+
+impl<'a> ByteContig<'a> {
+    /// The accession, with any invalid UTF-8 replaced by U+FFFD.
+    pub fn accession_lossy(&self) -> Cow<'a, str> {
+        String::from_utf8_lossy(self.accession)
+    }
+
+    /// The sequence ID, i.e. the first whitespace-delimited word of the FASTA header.
+    pub fn id(&self) -> &'a [u8] {
+        split_header(self.accession).0
+    }
+
+    /// The rest of the FASTA header after the ID, or `None` if the header has no description.
+    pub fn description(&self) -> Option<&'a [u8]> {
+        split_header(self.accession).1
+    }
+}
+
+/// Splits a FASTA header into its ID and description.
+fn split_header(header: &[u8]) -> (&[u8], Option<&[u8]>) {
+    let header = header.trim_ascii_start();
+    match header.iter().position(u8::is_ascii_whitespace) {
+        Some(end) => {
+            let description = header[end..].trim_ascii();
+            (&header[..end], Some(description).filter(|d| !d.is_empty()))
+        }
+        None => (header, None),
+    }
+}
+
+/// Parses the output of minCED without requiring the input to be valid UTF-8.
+///
+/// Only the accessions may contain arbitrary bytes. Everything else must be ASCII, as MinCED
+/// writes it, and repeats and spacers are only accepted if they consist of ASCII letters. As with
+/// [`parse`](crate::parse), nothing is returned if the input does not start with a `Sequence '`
+/// line, and parsing stops at the first contig that cannot be parsed or that is followed by text
+/// that is not another contig.
+pub fn parse_bytes(input: &[u8]) -> Result<Vec<ByteContig<'_>>, Err<Error<&[u8]>>> {
+    let mut contigs = vec![];
+    for range in record_ranges(input) {
+        match parse_byte_contig(&input[range]) {
+            Ok((remainder, contig)) => {
+                contigs.push(contig);
+                if !remainder.is_empty() {
+                    break;
+                }
+            }
+            Err(Err::Error(_)) => break,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(contigs)
+}
+
+/// Parses the accession and arrays for a single contig/genome.
+fn parse_byte_contig(input: &[u8]) -> IResult<&[u8], ByteContig<'_>> {
+    let (remainder, (accession, bp)) = parse_accession_line_bytes(input)?;
+    let text = match std::str::from_utf8(remainder) {
+        Ok(text) => text,
+        Err(e) => {
+            let invalid = &remainder[e.valid_up_to()..];
+            return Err(Err::Error(Error::new(invalid, ErrorKind::Char)));
+        }
+    };
+    match parse_arrays(text) {
+        Ok((rest, arrays)) => Ok((
+            &remainder[text.len() - rest.len()..],
+            ByteContig {
+                accession,
+                bp,
+                arrays,
+            },
+        )),
+        Err(e) => Err(e.map(|e| Error::new(e.input.as_bytes(), e.code))),
+    }
+}
+
+/// Parses the contig/genome accession and length. See `parse_accession_line` for how the end
+/// of the accession is found.
+fn parse_accession_line_bytes(input: &[u8]) -> IResult<&[u8], (&[u8], usize)> {
+    let (remainder, (_, line)) = pair(tag("Sequence '"), not_line_ending)(input)?;
+    match split_accession_line(line) {
+        Some((accession_len, bp)) => Ok((remainder, (&line[..accession_len], bp))),
+        None => Err(Err::Error(Error::new(line, ErrorKind::Tag))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_parse_bytes_matches_parse() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let expected = parse(&input).unwrap();
+        let actual = parse_bytes(input.as_bytes()).unwrap();
+        assert_eq!(expected.len(), actual.len());
+        for (e, a) in expected.iter().zip(actual.iter()) {
+            assert_eq!(e.accession.as_bytes(), a.accession);
+            assert_eq!(e.bp, a.bp);
+            assert_eq!(e.arrays, a.arrays);
+        }
+    }
+
+    #[test]
+    fn test_parse_bytes_stops_where_parse_does() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let preamble = format!("minced 0.4.2\n{}", input);
+        assert!(parse_bytes(preamble.as_bytes()).unwrap().is_empty());
+        let stray = input.replacen(
+            "Sequence 'MGYG000242676_4'",
+            "stray line\nSequence 'MGYG000242676_4'",
+            1,
+        );
+        assert_eq!(parse(&stray).unwrap().len(), 2);
+        assert_eq!(parse_bytes(stray.as_bytes()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_parse_bytes_latin1_accession() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let mut bytes = input
+            .replacen("MGYG000166779_43", "strain M\u{fc}ller", 1)
+            .into_bytes();
+        // Re-encode the u-umlaut as Latin-1, which is not valid UTF-8.
+        let position = bytes.windows(2).position(|w| w == [0xc3, 0xbc]).unwrap();
+        bytes.splice(position..position + 2, [0xfc]);
+        assert!(std::str::from_utf8(&bytes).is_err());
+
+        let contigs = parse_bytes(&bytes).unwrap();
+        assert_eq!(contigs.len(), 18);
+        assert_eq!(contigs[1].accession, b"strain M\xfcller");
+        assert_eq!(contigs[1].accession_lossy(), "strain M\u{fffd}ller");
+        assert_eq!(contigs[1].id(), b"strain");
+        assert_eq!(contigs[1].description(), Some(&b"M\xfcller"[..]));
+        assert_eq!(contigs[1].bp, 11302);
+        assert_eq!(contigs[1].arrays[0].repeat_spacers.len(), 22);
+    }
+
+    #[test]
+    fn test_parse_accession_line_bytes() {
+        let input = b"Sequence 'MGYG\xff000166779_38' (12280 bp)";
+        let (_, (accession, bp)) = parse_accession_line_bytes(input).unwrap();
+        assert_eq!(accession, b"MGYG\xff000166779_38");
+        assert_eq!(bp, 12280);
+    }
+
+    #[test]
+    fn test_parse_accession_line_bytes_with_quotes() {
+        let input = b"Sequence 'strain O'Brien \xe9 contig 1' (500 bp)\n";
+        let (remainder, (accession, bp)) = parse_accession_line_bytes(input).unwrap();
+        assert_eq!(accession, b"strain O'Brien \xe9 contig 1");
+        assert_eq!(bp, 500);
+        assert_eq!(remainder, b"\n");
+    }
+}
@@ -0,0 +1,316 @@
+//! Groups identical and near-identical spacers across many contigs.
+
+use crate::cluster::Clusterer;
+use crate::sequence::{edit_distance, hamming_distance, reverse_complement};
+use crate::{Contig, Repeat};
+use std::collections::HashMap;
+
+/// How the distance between two sequences is measured.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Distance {
+    /// The number of substitutions. Sequences of different lengths differ by at least the
+    /// difference in length.
+    Hamming,
+    /// The number of substitutions, insertions and deletions.
+    Edit,
+}
+
+impl Distance {
+    /// The distance between two sequences. Ambiguity codes match any base they stand for.
+    pub fn between(&self, a: &str, b: &str) -> usize {
+        match self {
+            Distance::Hamming => hamming_distance(a, b),
+            Distance::Edit => edit_distance(a, b),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Settings for building a [`SpacerCatalog`].
+pub struct CatalogOptions {
+    /// Whether a spacer and its reverse complement count as the same spacer.
+    pub reverse_complement: bool,
+    /// The largest distance at which two spacers are clustered together. With zero, only
+    /// identical spacers are grouped.
+    pub max_distance: usize,
+    /// How the distance between spacers is measured.
+    pub distance: Distance,
+}
+
+impl Default for CatalogOptions {
+    fn default() -> CatalogOptions {
+        CatalogOptions {
+            reverse_complement: false,
+            max_distance: 0,
+            distance: Distance::Hamming,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A single occurrence of a spacer.
+pub struct SpacerOccurrence<'a> {
+    /// Accession of the contig/genome the spacer is in.
+    pub accession: &'a str,
+    /// The order of the array the spacer is in (see [`Array::order`](crate::Array::order)).
+    pub order: usize,
+    /// The index of the spacer's unit in [`Array::repeat_spacers`](crate::Array::repeat_spacers).
+    pub index: usize,
+    /// Sequence of the spacer.
+    pub spacer: &'a str,
+    /// Whether the spacer matches the reverse complement of the cluster's representative.
+    pub reverse: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A group of identical or near-identical spacers.
+pub struct SpacerCluster<'a> {
+    /// The sequence standing for the whole cluster: the most common spacer in it (ignoring case,
+    /// and orientation if reverse complements are grouped), or the first one seen if there is a
+    /// tie.
+    pub representative: &'a str,
+    /// Every occurrence of a spacer in the cluster, in the order they were seen.
+    pub occurrences: Vec<SpacerOccurrence<'a>>,
+}
+
+impl<'a> SpacerCluster<'a> {
+    /// The number of occurrences in the cluster.
+    pub fn size(&self) -> usize {
+        self.occurrences.len()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Every spacer in a collection of contigs, grouped into clusters of identical or near-identical
+/// sequences.
+pub struct SpacerCatalog<'a> {
+    clusters: Vec<SpacerCluster<'a>>,
+}
+
+impl<'a> SpacerCatalog<'a> {
+    /// Builds a catalog of all of the spacers in `contigs`. Spacers are compared
+    /// case-insensitively.
+    ///
+    /// Identical spacers are grouped first. If `options.max_distance` is more than zero, the
+    /// groups are then clustered greedily, largest first: each group joins the cluster with the
+    /// closest representative within the distance, or starts a new cluster if there is none.
+    pub fn new(contigs: &[Contig<'a>], options: &CatalogOptions) -> SpacerCatalog<'a> {
+        let mut groups = group_identical(contigs, options.reverse_complement);
+        sort_groups(&mut groups);
+        if options.max_distance > 0 {
+            groups = cluster_groups(groups, options);
+            sort_groups(&mut groups);
+        }
+        let clusters = groups
+            .into_iter()
+            .map(|group| SpacerCluster {
+                representative: group.representative,
+                occurrences: group.occurrences.into_iter().map(|(_, o)| o).collect(),
+            })
+            .collect();
+        SpacerCatalog { clusters }
+    }
+
+    /// The clusters, largest first. Clusters of the same size are in the order they were seen.
+    pub fn clusters(&self) -> &[SpacerCluster<'a>] {
+        &self.clusters
+    }
+
+    /// The number of clusters.
+    pub fn len(&self) -> usize {
+        self.clusters.len()
+    }
+
+    /// Whether the catalog contains no spacers.
+    pub fn is_empty(&self) -> bool {
+        self.clusters.is_empty()
+    }
+}
+
+/// A cluster that is still being built. Each occurrence is numbered in the order it was seen.
+struct Group<'a> {
+    representative: &'a str,
+    occurrences: Vec<(usize, SpacerOccurrence<'a>)>,
+}
+
+/// Groups identical spacers. The first spacer seen in each group is its representative.
+fn group_identical<'a>(contigs: &[Contig<'a>], reverse_complement: bool) -> Vec<Group<'a>> {
+    let mut groups: Vec<Group<'a>> = vec![];
+    let mut keys: HashMap<String, usize> = HashMap::new();
+    let mut seen = 0;
+    for contig in contigs {
+        for array in &contig.arrays {
+            for (index, repeat) in array.repeat_spacers.iter().enumerate() {
+                let spacer = match repeat {
+                    Repeat::WithSpacer(rs) => rs.spacer,
+                    Repeat::WithoutSpacer(_) => continue,
+                };
+                seen += 1;
+                let key = spacer.to_ascii_uppercase();
+                let mut occurrence = SpacerOccurrence {
+                    accession: contig.accession,
+                    order: array.order,
+                    index,
+                    spacer,
+                    reverse: false,
+                };
+                if let Some(&group) = keys.get(&key) {
+                    groups[group].occurrences.push((seen, occurrence));
+                    continue;
+                }
+                if reverse_complement {
+                    if let Some(&group) = keys.get(&self::reverse_complement(&key)) {
+                        occurrence.reverse = true;
+                        groups[group].occurrences.push((seen, occurrence));
+                        continue;
+                    }
+                }
+                keys.insert(key, groups.len());
+                groups.push(Group {
+                    representative: spacer,
+                    occurrences: vec![(seen, occurrence)],
+                });
+            }
+        }
+    }
+    groups
+}
+
+/// Merges groups of identical spacers into clusters of near-identical ones. The groups must
+/// already be sorted, largest first.
+fn cluster_groups<'a>(groups: Vec<Group<'a>>, options: &CatalogOptions) -> Vec<Group<'a>> {
+    let mut clusters: Vec<Group<'a>> = vec![];
+    let mut clusterer = Clusterer::new(
+        options.max_distance,
+        options.reverse_complement,
+        |a: &str, b: &str| options.distance.between(a, b),
+    );
+    for mut group in groups {
+        let (cluster, _, is_reverse) = clusterer.assign(group.representative);
+        if cluster == clusters.len() {
+            clusters.push(group);
+            continue;
+        }
+        for (_, occurrence) in &mut group.occurrences {
+            occurrence.reverse ^= is_reverse;
+        }
+        clusters[cluster].occurrences.append(&mut group.occurrences);
+    }
+    clusters
+}
+
+/// Sorts groups largest first, then in the order they were first seen, and sorts the occurrences
+/// in each group in the order they were seen.
+fn sort_groups(groups: &mut [Group]) {
+    for group in groups.iter_mut() {
+        group.occurrences.sort_by_key(|&(seen, _)| seen);
+    }
+    groups.sort_by_key(|g| (std::cmp::Reverse(g.occurrences.len()), g.occurrences[0].0));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    const INPUT: &str = "Sequence 'a' (1000 bp)
+
+CRISPR 1   Range: 1 - 80
+POSITION	REPEAT	SPACER
+--------	-----	------
+1		GTTTCAAT	AAAACCCCGGGGTTTTACGT	[ 8, 20 ]
+29		GTTTCAAT	ACGTAAAACCCCGGGGTTTT	[ 8, 20 ]
+57		GTTTCAAT
+--------	-----	------
+Repeats: 3	Average Length: 8		Average Length: 20
+
+Time to find repeats: 1 ms
+
+
+Sequence 'b' (1000 bp)
+
+CRISPR 2   Range: 1 - 108
+POSITION	REPEAT	SPACER
+--------	-----	------
+1		GTTTCAAT	AAAACCCCGGGGTTTTACGT	[ 8, 20 ]
+29		GTTTCAAT	ACGTAAAACCCCGGGGTTTT	[ 8, 20 ]
+57		GTTTCAAT	ACGTAAAACCCCGGGGTTTA	[ 8, 20 ]
+85		GTTTCAAT	acgtaaaaccccggggtttt	[ 8, 20 ]
+113		GTTTCAAT
+--------	-----	------
+Repeats: 5	Average Length: 8		Average Length: 20
+
+Time to find repeats: 1 ms
+
+
+";
+
+    fn sizes(catalog: &SpacerCatalog) -> Vec<usize> {
+        catalog.clusters().iter().map(|c| c.size()).collect()
+    }
+
+    #[test]
+    fn test_identical_spacers() {
+        let contigs = parse(INPUT).unwrap();
+        let catalog = SpacerCatalog::new(&contigs, &CatalogOptions::default());
+        assert_eq!(sizes(&catalog), vec![3, 2, 1]);
+        let cluster = &catalog.clusters()[0];
+        assert_eq!(cluster.representative, "ACGTAAAACCCCGGGGTTTT");
+        let locations: Vec<(&str, usize, usize)> = cluster
+            .occurrences
+            .iter()
+            .map(|o| (o.accession, o.order, o.index))
+            .collect();
+        assert_eq!(locations, vec![("a", 0, 1), ("b", 1, 1), ("b", 1, 3)]);
+    }
+
+    #[test]
+    fn test_reverse_complement() {
+        let contigs = parse(INPUT).unwrap();
+        let options = CatalogOptions {
+            reverse_complement: true,
+            ..CatalogOptions::default()
+        };
+        let catalog = SpacerCatalog::new(&contigs, &options);
+        // AAAACCCCGGGGTTTTACGT is the reverse complement of ACGTAAAACCCCGGGGTTTT.
+        assert_eq!(sizes(&catalog), vec![5, 1]);
+        let cluster = &catalog.clusters()[0];
+        assert_eq!(cluster.representative, "AAAACCCCGGGGTTTTACGT");
+        let reverse: Vec<bool> = cluster.occurrences.iter().map(|o| o.reverse).collect();
+        assert_eq!(reverse, vec![false, true, false, true, true]);
+    }
+
+    #[test]
+    fn test_near_identical_spacers() {
+        let contigs = parse(INPUT).unwrap();
+        let options = CatalogOptions {
+            max_distance: 1,
+            ..CatalogOptions::default()
+        };
+        let catalog = SpacerCatalog::new(&contigs, &options);
+        assert_eq!(sizes(&catalog), vec![4, 2]);
+        assert_eq!(catalog.clusters()[0].representative, "ACGTAAAACCCCGGGGTTTT");
+
+        let options = CatalogOptions {
+            reverse_complement: true,
+            max_distance: 1,
+            distance: Distance::Edit,
+        };
+        let catalog = SpacerCatalog::new(&contigs, &options);
+        assert_eq!(sizes(&catalog), vec![6]);
+        assert_eq!(catalog.len(), 1);
+    }
+
+    #[test]
+    fn test_ambiguity_codes() {
+        let input = INPUT.replace("ACGTAAAACCCCGGGGTTTA", "ACGTNAAACCCCGNGGTTTN");
+        let contigs = parse(&input).unwrap();
+        let options = CatalogOptions {
+            max_distance: 1,
+            ..CatalogOptions::default()
+        };
+        // The Ns match the bases they replace, so the spacer still joins the largest cluster.
+        let catalog = SpacerCatalog::new(&contigs, &options);
+        assert_eq!(sizes(&catalog), vec![4, 2]);
+    }
+}
@@ -0,0 +1,58 @@
+//! Greedy clustering of sequences around representatives, shared by spacer catalogs and repeat
+//! families.
+
+use crate::seeds::SeedIndex;
+use crate::sequence::reverse_complement;
+
+/// Assigns sequences, one at a time, to the closest representative within `max_distance`. A
+/// sequence with no representative that close becomes the representative of a new cluster.
+pub(crate) struct Clusterer<F> {
+    max_distance: usize,
+    reverse_complement: bool,
+    distance: F,
+    index: SeedIndex,
+    representatives: Vec<String>,
+}
+
+impl<F: Fn(&str, &str) -> usize> Clusterer<F> {
+    /// With `reverse_complement`, a sequence can also join a cluster through its reverse
+    /// complement.
+    pub(crate) fn new(max_distance: usize, reverse_complement: bool, distance: F) -> Clusterer<F> {
+        Clusterer {
+            max_distance,
+            reverse_complement,
+            distance,
+            index: SeedIndex::new(max_distance),
+            representatives: vec![],
+        }
+    }
+
+    /// Assigns a sequence to a cluster, numbered in the order the clusters were started, and
+    /// returns the cluster, the distance to its representative and whether the sequence matched
+    /// the representative's reverse complement. Ties go to the cluster started first, and the
+    /// forward strand.
+    pub(crate) fn assign(&mut self, sequence: &str) -> (usize, usize, bool) {
+        let reverse = self
+            .reverse_complement
+            .then(|| reverse_complement(sequence));
+        let mut best: Option<(usize, usize, bool)> = None;
+        for (query, is_reverse) in [(Some(sequence), false), (reverse.as_deref(), true)] {
+            let query = match query {
+                Some(query) => query,
+                None => continue,
+            };
+            for candidate in self.index.candidates(query) {
+                let distance = (self.distance)(query, &self.representatives[candidate]);
+                if distance <= self.max_distance && best.is_none_or(|(_, d, _)| distance < d) {
+                    best = Some((candidate, distance, is_reverse));
+                }
+            }
+        }
+        best.unwrap_or_else(|| {
+            let cluster = self.representatives.len();
+            self.index.insert(cluster, sequence);
+            self.representatives.push(sequence.to_string());
+            (cluster, 0, false)
+        })
+    }
+}
@@ -0,0 +1,266 @@
+//! Flags low-complexity and internally repetitive spacers, which match far more targets than a
+//! real spacer would and so cause false protospacer hits.
+
+use crate::escape::escape_json;
+use crate::sequence::{base_mask, bases_match, entropy};
+use crate::{Contig, Repeat, RepeatSpacer, SpacerCluster};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+#[derive(Clone, Debug, PartialEq)]
+/// Thresholds for flagging a spacer as low complexity.
+pub struct ComplexityOptions {
+    /// The lowest entropy of the base composition, in bits per base.
+    pub min_entropy: f64,
+    /// The highest DUST score.
+    pub max_dust_score: f64,
+    /// The fraction of bases that must match the base one period later for a spacer to count as
+    /// a tandem repeat of that period.
+    pub min_period_identity: f64,
+}
+
+impl Default for ComplexityOptions {
+    fn default() -> ComplexityOptions {
+        ComplexityOptions {
+            min_entropy: 1.5,
+            max_dust_score: 2.0,
+            min_period_identity: 0.9,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// How complex a spacer's sequence is.
+pub struct SpacerComplexity {
+    /// The entropy of the base composition, in bits per base.
+    pub entropy: f64,
+    /// The DUST score: for each of the 64 triplets, the number of pairs of occurrences of that
+    /// triplet, summed and divided by one less than the number of triplets. Random sequence
+    /// scores close to zero.
+    pub dust_score: f64,
+    /// The shortest period at which the spacer repeats itself at least three times, if there is
+    /// one.
+    pub period: Option<usize>,
+    /// Whether the spacer failed any of the thresholds.
+    pub low_complexity: bool,
+}
+
+/// Measures the complexity of a spacer sequence.
+pub fn spacer_complexity(spacer: &str, options: &ComplexityOptions) -> SpacerComplexity {
+    let entropy = entropy(spacer);
+    let dust_score = dust_score(spacer);
+    let period = tandem_period(spacer, options.min_period_identity);
+    SpacerComplexity {
+        entropy,
+        dust_score,
+        period,
+        low_complexity: entropy < options.min_entropy
+            || dust_score > options.max_dust_score
+            || period.is_some(),
+    }
+}
+
+/// The DUST score of a sequence. Triplets containing anything other than A, C, G or T are
+/// skipped.
+fn dust_score(sequence: &str) -> f64 {
+    let mut counts: HashMap<[u8; 3], usize> = HashMap::new();
+    let mut triplets = 0;
+    for window in sequence.as_bytes().windows(3) {
+        if window.iter().all(|&b| base_mask(b).is_power_of_two()) {
+            let triplet = [
+                window[0].to_ascii_uppercase(),
+                window[1].to_ascii_uppercase(),
+                window[2].to_ascii_uppercase(),
+            ];
+            *counts.entry(triplet).or_default() += 1;
+            triplets += 1;
+        }
+    }
+    if triplets < 2 {
+        return 0.0;
+    }
+    let pairs: usize = counts.values().map(|&c| c * (c - 1) / 2).sum();
+    pairs as f64 / (triplets - 1) as f64
+}
+
+/// The shortest period, up to a third of the sequence's length, at which at least
+/// `min_identity` of the bases match the base one period later.
+fn tandem_period(sequence: &str, min_identity: f64) -> Option<usize> {
+    let bases = sequence.as_bytes();
+    (1..=bases.len() / 3).find(|&period| {
+        let compared = bases.len() - period;
+        let matching = bases
+            .iter()
+            .zip(&bases[period..])
+            .filter(|(&a, &b)| bases_match(a, b))
+            .count();
+        matching as f64 >= min_identity * compared as f64
+    })
+}
+
+impl<'a> RepeatSpacer<'a> {
+    /// Measures the complexity of this spacer.
+    pub fn complexity(&self, options: &ComplexityOptions) -> SpacerComplexity {
+        spacer_complexity(self.spacer, options)
+    }
+}
+
+impl<'a> SpacerCluster<'a> {
+    /// Measures the complexity of the cluster's representative spacer.
+    pub fn complexity(&self, options: &ComplexityOptions) -> SpacerComplexity {
+        spacer_complexity(self.representative, options)
+    }
+}
+
+/// The complexity of every spacer in some contigs, with the accession, the array's order and the
+/// index of the spacer's repeat-spacer unit.
+fn spacer_rows<'c>(
+    contigs: &'c [Contig],
+    options: &'c ComplexityOptions,
+) -> impl Iterator<Item = (&'c str, usize, usize, &'c str, SpacerComplexity)> {
+    contigs.iter().flat_map(move |contig| {
+        contig.arrays.iter().flat_map(move |array| {
+            array.repeat_spacers.iter().enumerate().filter_map(
+                move |(index, repeat)| match repeat {
+                    Repeat::WithSpacer(rs) => Some((
+                        contig.accession,
+                        array.order,
+                        index,
+                        rs.spacer,
+                        rs.complexity(options),
+                    )),
+                    Repeat::WithoutSpacer(_) => None,
+                },
+            )
+        })
+    })
+}
+
+/// Writes the complexity of every spacer as tab-separated values, one spacer per line after a
+/// header line. The columns are the accession, the array's order, the index of the spacer's
+/// repeat-spacer unit, the spacer, its entropy, its DUST score, its tandem period (empty if it
+/// has none) and whether it is low complexity.
+pub fn write_complexity_tsv<W: Write>(
+    contigs: &[Contig],
+    options: &ComplexityOptions,
+    mut writer: W,
+) -> io::Result<()> {
+    writeln!(
+        writer,
+        "accession\tarray\tindex\tspacer\tentropy\tdust_score\tperiod\tlow_complexity"
+    )?;
+    for (accession, order, index, spacer, complexity) in spacer_rows(contigs, options) {
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}\t{:.3}\t{:.3}\t{}\t{}",
+            accession,
+            order,
+            index,
+            spacer,
+            complexity.entropy,
+            complexity.dust_score,
+            complexity.period.map_or(String::new(), |p| p.to_string()),
+            complexity.low_complexity
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes the complexity of every spacer as a JSON array with one object per spacer, with the
+/// keys `accession`, `array`, `index`, `spacer`, `entropy`, `dust_score`, `period` (`null` if the
+/// spacer has none) and `low_complexity`.
+pub fn write_complexity_json<W: Write>(
+    contigs: &[Contig],
+    options: &ComplexityOptions,
+    mut writer: W,
+) -> io::Result<()> {
+    write!(writer, "[")?;
+    for (i, (accession, order, index, spacer, complexity)) in
+        spacer_rows(contigs, options).enumerate()
+    {
+        if i > 0 {
+            write!(writer, ",")?;
+        }
+        write!(
+            writer,
+            "{{\"accession\":\"{}\",\"array\":{},\"index\":{},\"spacer\":\"{}\",\"entropy\":{},\"dust_score\":{},\"period\":{},\"low_complexity\":{}}}",
+            escape_json(accession),
+            order,
+            index,
+            escape_json(spacer),
+            complexity.entropy,
+            complexity.dust_score,
+            complexity.period.map_or("null".to_string(), |p| p.to_string()),
+            complexity.low_complexity
+        )?;
+    }
+    writeln!(writer, "]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_spacer_complexity() {
+        let options = ComplexityOptions::default();
+        let real = spacer_complexity("ACGGGTGCACTTTCGATGTCGCACTTTTTG", &options);
+        assert!(!real.low_complexity);
+        assert_eq!(real.period, None);
+
+        let poly_a = spacer_complexity("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAA", &options);
+        assert!(poly_a.low_complexity);
+        assert_eq!(poly_a.entropy, 0.0);
+        assert_eq!(poly_a.dust_score, 14.0);
+        assert_eq!(poly_a.period, Some(1));
+
+        // High entropy, but a tandem repeat of ACGTTG.
+        let tandem = spacer_complexity("ACGTTGACGTTGACGTTGACGTTGACGATG", &options);
+        assert!(tandem.entropy > 1.9);
+        assert_eq!(tandem.period, Some(6));
+        assert!(tandem.low_complexity);
+    }
+
+    #[test]
+    fn test_dust_score() {
+        assert_eq!(dust_score(""), 0.0);
+        assert_eq!(dust_score("ACGTA"), 0.0);
+        assert_eq!(dust_score("ACGACG"), 1.0 / 3.0);
+        assert_eq!(dust_score("ACGNACG"), 1.0);
+    }
+
+    #[test]
+    fn test_write_complexity_tsv() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let contigs = parse(&input).unwrap();
+        let mut output = vec![];
+        write_complexity_tsv(&contigs[..1], &ComplexityOptions::default(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with(
+            "MGYG000166779_38\t0\t0\tGGGGGTGCACTTAAAGGGGGTGCACTTGTCTCAAGTGCACCAAGAA\t"
+        ));
+        assert!(lines[2].starts_with("MGYG000166779_38\t0\t1\t"));
+    }
+
+    #[test]
+    fn test_write_complexity_json() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let contigs = parse(&input).unwrap();
+        let mut output = vec![];
+        write_complexity_json(&contigs[..1], &ComplexityOptions::default(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with(
+            "[{\"accession\":\"MGYG000166779_38\",\"array\":0,\"index\":0,\"spacer\":\"GGGGGTGCACTTAAAGGGGGTGCACTTGTCTCAAGTGCACCAAGAA\",\"entropy\":"
+        ));
+        assert!(output.contains("\"period\":null,\"low_complexity\":false}"));
+        assert!(output.contains("},{\"accession\":\"MGYG000166779_38\",\"array\":0,\"index\":1,"));
+        assert!(output.ends_with("}]\n"));
+
+        let mut empty = vec![];
+        write_complexity_json(&[], &ComplexityOptions::default(), &mut empty).unwrap();
+        assert_eq!(empty, b"[]\n");
+    }
+}
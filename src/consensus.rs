@@ -0,0 +1,145 @@
+//! Consensus repeats and how far each repeat in an array deviates from them.
+
+use crate::sequence::{base_mask, hamming_distance, iupac_code};
+use crate::{Array, Repeat};
+
+/// Counts of A, C, G and T in one column of an array's repeats.
+type ColumnCounts = [usize; 4];
+
+impl<'a> Array<'a> {
+    /// The majority-rule consensus of all of the repeats in this array. Where two or more bases
+    /// are equally common in a column, the IUPAC code covering all of them is used instead.
+    ///
+    /// If the repeats differ in length, the consensus has the most common length (the longest,
+    /// if that is tied).
+    pub fn consensus_repeat(&self) -> String {
+        let consensus: Vec<u8> = self
+            .repeat_columns()
+            .iter()
+            .map(|counts| {
+                let most = counts.iter().copied().max().unwrap_or(0);
+                let mask = counts
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &count)| most > 0 && count == most)
+                    .fold(0, |mask, (i, _)| mask | 1 << i);
+                iupac_code(mask)
+            })
+            .collect();
+        String::from_utf8(consensus).expect("IUPAC codes are ASCII")
+    }
+
+    /// The variability of each column of the consensus repeat: the fraction of repeats whose base
+    /// in that column differs from the most common one. Zero means the column is fully conserved.
+    pub fn repeat_variability(&self) -> Vec<f64> {
+        self.repeat_columns()
+            .iter()
+            .map(|counts| {
+                let total: usize = counts.iter().sum();
+                let most = counts.iter().copied().max().unwrap_or(0);
+                if total == 0 {
+                    0.0
+                } else {
+                    (total - most) as f64 / total as f64
+                }
+            })
+            .collect()
+    }
+
+    /// The Hamming distance between each repeat and the consensus repeat, in array order.
+    pub fn repeat_distances(&self) -> Vec<usize> {
+        let consensus = self.consensus_repeat();
+        self.repeat_spacers
+            .iter()
+            .map(|r| r.distance_to(&consensus))
+            .collect()
+    }
+
+    /// Counts the bases in each column of the repeats, up to the length of the consensus.
+    fn repeat_columns(&self) -> Vec<ColumnCounts> {
+        let length = consensus_length(self.repeat_spacers.iter().map(|r| r.repeat().len()));
+        let mut columns = vec![[0; 4]; length];
+        for repeat in &self.repeat_spacers {
+            for (counts, base) in columns.iter_mut().zip(repeat.repeat().bytes()) {
+                // Ambiguous bases in the repeats themselves are not counted.
+                let mask = base_mask(base);
+                if mask.is_power_of_two() {
+                    counts[mask.trailing_zeros() as usize] += 1;
+                }
+            }
+        }
+        columns
+    }
+}
+
+impl<'a> Repeat<'a> {
+    /// The Hamming distance between this repeat and `consensus`. Ambiguity codes in the
+    /// consensus match any base they stand for, and a difference in length counts as that many
+    /// mismatches.
+    pub fn distance_to(&self, consensus: &str) -> usize {
+        hamming_distance(self.repeat(), consensus)
+    }
+}
+
+/// The most common of the given lengths, preferring the longest if there is a tie.
+fn consensus_length(lengths: impl Iterator<Item = usize>) -> usize {
+    let mut lengths: Vec<usize> = lengths.collect();
+    lengths.sort_unstable();
+    let mut best = (0, 0);
+    for run in lengths.chunk_by(|a, b| a == b) {
+        if run.len() >= best.1 {
+            best = (run[0], run.len());
+        }
+    }
+    best.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_consensus_repeat() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let contigs = parse(&input).unwrap();
+        let array = &contigs[1].arrays[0];
+        assert_eq!(
+            array.consensus_repeat(),
+            "GTTGTGGTTTGATGTAGGAATCAAAAGATATACAAC"
+        );
+        let distances = array.repeat_distances();
+        assert_eq!(distances.len(), 22);
+        assert!(distances[..21].iter().all(|&d| d == 0));
+        assert_eq!(distances[21], 2);
+
+        let variability = array.repeat_variability();
+        assert_eq!(variability.len(), 36);
+        assert_eq!(variability[0], 0.0);
+        assert_eq!(variability[17], 1.0 / 22.0);
+        assert_eq!(variability[28], 1.0 / 22.0);
+    }
+
+    #[test]
+    fn test_consensus_repeat_ties() {
+        let input = "\nCRISPR 1   Range: 1 - 20
+POSITION	REPEAT	SPACER
+--------	-----	------
+1		ACGTA	GGGGG	[ 5, 5 ]
+11		ACCTA
+--------	-----	------
+Repeats: 2	Average Length: 5		Average Length: 5
+";
+        let (_, array) = crate::parse_array(input).unwrap();
+        assert_eq!(array.consensus_repeat(), "ACSTA");
+        assert_eq!(array.repeat_distances(), vec![0, 0]);
+        assert_eq!(array.repeat_variability(), vec![0.0, 0.0, 0.5, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_consensus_length() {
+        assert_eq!(consensus_length([36, 36, 35].into_iter()), 36);
+        assert_eq!(consensus_length([35, 36].into_iter()), 36);
+        assert_eq!(consensus_length([].into_iter()), 0);
+    }
+}
@@ -0,0 +1,77 @@
+//! Flags repeats that deviate from their array's consensus.
+
+use crate::sequence::mismatch_positions;
+use crate::{Array, Repeat};
+
+#[derive(Debug, PartialEq)]
+/// A repeat that differs from its array's consensus repeat by more than the allowed number of
+/// mismatches. The terminal repeat is often degenerate, and marks where the trailer begins.
+pub struct DegenerateRepeat {
+    /// The order of the array the repeat belongs to (see [`Array::order`]).
+    pub order: usize,
+    /// The index of the repeat in [`Array::repeat_spacers`].
+    pub index: usize,
+    /// Whether this is the final repeat of the array, which has no spacer.
+    pub terminal: bool,
+    /// The number of positions that differ from the consensus.
+    pub mismatches: usize,
+    /// The zero-indexed positions within the repeat that differ from the consensus. A difference
+    /// in length counts as a mismatch at each position past the end of the shorter sequence.
+    pub positions: Vec<usize>,
+}
+
+impl<'a> Array<'a> {
+    /// Finds the repeats in this array with more than `max_mismatches` differences from the
+    /// consensus repeat, in array order.
+    pub fn degenerate_repeats(&self, max_mismatches: usize) -> Vec<DegenerateRepeat> {
+        let consensus = self.consensus_repeat();
+        self.repeat_spacers
+            .iter()
+            .enumerate()
+            .filter_map(|(index, repeat)| {
+                let positions =
+                    mismatch_positions(repeat.repeat().as_bytes(), consensus.as_bytes());
+                if positions.len() <= max_mismatches {
+                    return None;
+                }
+                Some(DegenerateRepeat {
+                    order: self.order,
+                    index,
+                    terminal: matches!(repeat, Repeat::WithoutSpacer(_)),
+                    mismatches: positions.len(),
+                    positions,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_degenerate_repeats() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let contigs = parse(&input).unwrap();
+
+        let array = &contigs[1].arrays[0];
+        let expected = vec![DegenerateRepeat {
+            order: 1,
+            index: 21,
+            terminal: true,
+            mismatches: 2,
+            positions: vec![17, 28],
+        }];
+        assert_eq!(array.degenerate_repeats(0), expected);
+        assert!(array.degenerate_repeats(2).is_empty());
+
+        let array = &contigs[2].arrays[0];
+        let degenerate = array.degenerate_repeats(1);
+        let indices: Vec<usize> = degenerate.iter().map(|d| d.index).collect();
+        assert_eq!(indices, vec![1, 4, 8]);
+        assert!(!degenerate[0].terminal);
+        assert!(degenerate[2].terminal);
+    }
+}
@@ -0,0 +1,45 @@
+//! Escapes text for the formats that arrays, graphs and summaries are exported in.
+
+/// Escapes text for use inside a JSON string literal.
+pub(crate) fn escape_json(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escapes text for a double-quoted DOT string.
+pub(crate) fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Escapes text for XML character data.
+pub(crate) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape_json(r#"b "O'Brien"\x"#), r#"b \"O'Brien\"\\x"#);
+        assert_eq!(escape_json("a\tb"), "a\\u0009b");
+        assert_eq!(escape_dot(r#"b "O'Brien"\x"#), r#"b \"O'Brien\"\\x"#);
+        assert_eq!(
+            escape_xml(r#"<b "O'Brien" & co>"#),
+            "&lt;b &quot;O&apos;Brien&quot; &amp; co&gt;"
+        );
+    }
+}
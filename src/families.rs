@@ -0,0 +1,251 @@
+//! Groups arrays into repeat families by the similarity of their consensus repeats.
+
+use crate::cluster::Clusterer;
+use crate::sequence::{edit_distance, reverse_complement};
+use crate::{ArrayId, Contig};
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Clone, Debug, PartialEq)]
+/// Settings for finding [`RepeatFamilies`].
+pub struct FamilyOptions {
+    /// The largest edit distance between an array's consensus repeat and a family's
+    /// representative at which the array joins the family.
+    pub max_distance: usize,
+    /// Whether a repeat and its reverse complement count as the same repeat. MinCED reports
+    /// every array on the forward strand, so the same system can appear in either orientation.
+    pub reverse_complement: bool,
+}
+
+impl Default for FamilyOptions {
+    fn default() -> FamilyOptions {
+        FamilyOptions {
+            max_distance: 3,
+            reverse_complement: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// An array that belongs to a repeat family.
+pub struct FamilyMember<'a> {
+    /// The array.
+    pub array: ArrayId<'a>,
+    /// The array's consensus repeat.
+    pub consensus: String,
+    /// The edit distance between the consensus repeat and the family's representative.
+    pub distance: usize,
+    /// Whether the consensus repeat matches the reverse complement of the representative.
+    pub reverse: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A group of arrays with similar consensus repeats.
+pub struct RepeatFamily<'a> {
+    /// The family's ID, which is its position in [`RepeatFamilies::families`].
+    pub id: usize,
+    /// The consensus repeat that stands for the family: the most common one among its members,
+    /// or the first one seen if there is a tie.
+    pub representative: String,
+    /// The arrays in the family, in the order they were seen.
+    pub members: Vec<FamilyMember<'a>>,
+}
+
+impl<'a> RepeatFamily<'a> {
+    /// The number of arrays in the family.
+    pub fn size(&self) -> usize {
+        self.members.len()
+    }
+
+    /// The number of distinct contigs/genomes with an array in the family.
+    pub fn contig_count(&self) -> usize {
+        let accessions: BTreeSet<&str> = self.members.iter().map(|m| m.array.accession).collect();
+        accessions.len()
+    }
+
+    /// The mean edit distance between the members' consensus repeats and the representative.
+    pub fn mean_distance(&self) -> f64 {
+        let total: usize = self.members.iter().map(|m| m.distance).sum();
+        total as f64 / self.members.len() as f64
+    }
+
+    /// The largest edit distance between a member's consensus repeat and the representative.
+    pub fn max_distance(&self) -> usize {
+        self.members.iter().map(|m| m.distance).max().unwrap_or(0)
+    }
+
+    /// The mean length of the members' consensus repeats.
+    pub fn mean_repeat_length(&self) -> f64 {
+        let total: usize = self.members.iter().map(|m| m.consensus.len()).sum();
+        total as f64 / self.members.len() as f64
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Every array in a collection of contigs, grouped into repeat families.
+pub struct RepeatFamilies<'a> {
+    families: Vec<RepeatFamily<'a>>,
+    assignments: HashMap<ArrayId<'a>, usize>,
+}
+
+impl<'a> RepeatFamilies<'a> {
+    /// Groups the arrays in `contigs` into families by the edit distance between their consensus
+    /// repeats, which are compared case-insensitively.
+    ///
+    /// Arrays with identical consensus repeats are grouped first, and the groups are then
+    /// clustered greedily, largest first: each group joins the family with the closest
+    /// representative within `options.max_distance`, or starts a new family if there is none.
+    pub fn new(contigs: &[Contig<'a>], options: &FamilyOptions) -> RepeatFamilies<'a> {
+        let mut families: Vec<RepeatFamily<'a>> = vec![];
+        let mut clusterer = Clusterer::new(
+            options.max_distance,
+            options.reverse_complement,
+            edit_distance,
+        );
+        for group in group_identical(contigs, options.reverse_complement) {
+            let (family, distance, is_reverse) = clusterer.assign(&group[0].consensus);
+            if family == families.len() {
+                families.push(RepeatFamily {
+                    id: family,
+                    representative: group[0].consensus.clone(),
+                    members: vec![],
+                });
+            }
+            for mut member in group {
+                member.distance = distance;
+                member.reverse ^= is_reverse;
+                families[family].members.push(member);
+            }
+        }
+
+        // Number the families largest first, then in the order they were first seen.
+        let seen: HashMap<ArrayId, usize> = contigs
+            .iter()
+            .flat_map(|c| c.arrays.iter().map(move |a| ArrayId::new(c, a)))
+            .enumerate()
+            .map(|(i, id)| (id, i))
+            .collect();
+        for family in &mut families {
+            family.members.sort_by_key(|m| seen[&m.array]);
+        }
+        families.sort_by_key(|f| (std::cmp::Reverse(f.size()), seen[&f.members[0].array]));
+        let mut assignments = HashMap::new();
+        for (id, family) in families.iter_mut().enumerate() {
+            family.id = id;
+            for member in &family.members {
+                assignments.insert(member.array, id);
+            }
+        }
+        RepeatFamilies {
+            families,
+            assignments,
+        }
+    }
+
+    /// The families, largest first. Families of the same size are in the order they were seen.
+    pub fn families(&self) -> &[RepeatFamily<'a>] {
+        &self.families
+    }
+
+    /// The ID of the family an array belongs to, or `None` if the array was not in the
+    /// collection.
+    pub fn family_of(&self, array: &ArrayId) -> Option<usize> {
+        self.assignments.get(array).copied()
+    }
+
+    /// The family an array belongs to, or `None` if the array was not in the collection.
+    pub fn family(&self, array: &ArrayId) -> Option<&RepeatFamily<'a>> {
+        self.family_of(array).map(|id| &self.families[id])
+    }
+}
+
+/// Groups arrays with identical consensus repeats, largest group first. The consensus of each
+/// group's first member is the one the others are compared to.
+fn group_identical<'a>(
+    contigs: &[Contig<'a>],
+    reverse_complement: bool,
+) -> Vec<Vec<FamilyMember<'a>>> {
+    let mut groups: Vec<Vec<FamilyMember<'a>>> = vec![];
+    let mut keys: HashMap<String, usize> = HashMap::new();
+    for contig in contigs {
+        for array in &contig.arrays {
+            let consensus = array.consensus_repeat();
+            let key = consensus.to_ascii_uppercase();
+            let mut member = FamilyMember {
+                array: ArrayId::new(contig, array),
+                consensus,
+                distance: 0,
+                reverse: false,
+            };
+            let existing = keys.get(&key).copied().or_else(|| {
+                if !reverse_complement {
+                    return None;
+                }
+                let group = keys.get(&self::reverse_complement(&key)).copied();
+                member.reverse = group.is_some();
+                group
+            });
+            match existing {
+                Some(group) => groups[group].push(member),
+                None => {
+                    keys.insert(key, groups.len());
+                    groups.push(vec![member]);
+                }
+            }
+        }
+    }
+    // A stable sort, so groups of the same size stay in the order they were seen.
+    groups.sort_by_key(|g| std::cmp::Reverse(g.len()));
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_repeat_families() {
+        let mut input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        // Add a reverse complemented copy of the first contig, with one substitution.
+        let first = &input[..input.find("Sequence 'MGYG000166779_43'").unwrap()];
+        let copy = first.replace("MGYG000166779_38", "copy").replace(
+            "CAAGTGCACCAACCAATCTCACCACCTCA",
+            "TGAGGTGGTGAGATTGGTTGGTGCAATTG",
+        );
+        input.push_str(&copy);
+        let contigs = parse(&input).unwrap();
+        let families = RepeatFamilies::new(&contigs, &FamilyOptions::default());
+
+        let original = ArrayId {
+            accession: "MGYG000166779_38",
+            order: 0,
+        };
+        let copy = ArrayId {
+            accession: "copy",
+            order: 0,
+        };
+        let family = families.family(&original).unwrap();
+        assert_eq!(families.family_of(&copy), Some(family.id));
+        assert_eq!(family.size(), 2);
+        assert_eq!(family.contig_count(), 2);
+        assert_eq!(family.representative, "CAAGTGCACCAACCAATCTCACCACCTCA");
+        assert!(!family.members[0].reverse);
+        assert!(family.members[1].reverse);
+        assert_eq!(family.members[1].distance, 1);
+        assert_eq!(family.mean_distance(), 0.5);
+        assert_eq!(family.max_distance(), 1);
+        assert_eq!(family.mean_repeat_length(), 29.0);
+
+        let strict = FamilyOptions {
+            max_distance: 3,
+            reverse_complement: false,
+        };
+        let families = RepeatFamilies::new(&contigs, &strict);
+        assert_ne!(families.family_of(&original), families.family_of(&copy));
+        for (id, family) in families.families().iter().enumerate() {
+            assert_eq!(family.id, id);
+        }
+        let sizes: Vec<usize> = families.families().iter().map(|f| f.size()).collect();
+        assert!(sizes.windows(2).all(|w| w[0] >= w[1]));
+    }
+}
@@ -0,0 +1,275 @@
+//! A minimal FASTA reader, for looking up sequences that MinCED does not report.
+
+use crate::split_header;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+#[derive(Clone, Debug, PartialEq)]
+/// A single sequence from a FASTA file.
+pub struct FastaRecord {
+    /// The full header line, without the leading `>`.
+    pub header: String,
+    /// The sequence, with line breaks removed.
+    pub sequence: Vec<u8>,
+}
+
+impl FastaRecord {
+    /// The sequence ID, i.e. the first whitespace-delimited word of the header.
+    pub fn id(&self) -> &str {
+        split_header(&self.header).0
+    }
+
+    /// The rest of the header after the ID, or `None` if the header has no description.
+    pub fn description(&self) -> Option<&str> {
+        split_header(&self.header).1
+    }
+}
+
+/// Reads FASTA records one at a time.
+pub struct FastaReader<R> {
+    reader: R,
+    header: Option<String>,
+    line: String,
+}
+
+impl FastaReader<Box<dyn BufRead>> {
+    /// Opens a FASTA file, which may be gzipped if the `gzip` feature is enabled. Gzipped files
+    /// are recognised by their contents rather than their name.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<FastaReader<Box<dyn BufRead>>> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let gzipped = reader.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+        let reader: Box<dyn BufRead> = if gzipped {
+            gunzip(reader)?
+        } else {
+            Box::new(reader)
+        };
+        Ok(FastaReader::new(reader))
+    }
+}
+
+impl<R: BufRead> FastaReader<R> {
+    /// Reads FASTA records from `reader`.
+    pub fn new(reader: R) -> FastaReader<R> {
+        FastaReader {
+            reader,
+            header: None,
+            line: String::new(),
+        }
+    }
+
+    /// Reads the next record, or returns `None` at the end of the input.
+    fn read_record(&mut self) -> io::Result<Option<FastaRecord>> {
+        let mut sequence = vec![];
+        loop {
+            self.line.clear();
+            let eof = self.reader.read_line(&mut self.line)? == 0;
+            let line = self.line.trim_end_matches(['\n', '\r']);
+            if eof || line.starts_with('>') {
+                let next = (!eof).then(|| line[1..].to_string());
+                match std::mem::replace(&mut self.header, next) {
+                    Some(header) => return Ok(Some(FastaRecord { header, sequence })),
+                    None if eof => return Ok(None),
+                    None => continue,
+                }
+            }
+            if self.header.is_none() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "FASTA sequence found before the first header",
+                ));
+            }
+            sequence.extend(line.bytes().filter(|b| !b.is_ascii_whitespace()));
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for FastaReader<R> {
+    type Item = io::Result<FastaRecord>;
+
+    fn next(&mut self) -> Option<io::Result<FastaRecord>> {
+        self.read_record().transpose()
+    }
+}
+
+/// Where a sequence is in a FASTA file, from one line of a samtools `.fai` index.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct FaiEntry {
+    length: usize,
+    offset: u64,
+    line_bases: usize,
+    line_width: usize,
+}
+
+/// A FASTA file with a samtools `.fai` index, from which any region of any sequence can be read
+/// without reading the rest of the file.
+pub struct IndexedFasta<R> {
+    reader: R,
+    index: HashMap<String, FaiEntry>,
+}
+
+impl IndexedFasta<BufReader<File>> {
+    /// Opens an uncompressed FASTA file and the index next to it, with `.fai` added to its name.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<IndexedFasta<BufReader<File>>> {
+        let path = path.as_ref();
+        let mut index_path = path.as_os_str().to_owned();
+        index_path.push(".fai");
+        let index = BufReader::new(File::open(index_path)?);
+        IndexedFasta::new(BufReader::new(File::open(path)?), index)
+    }
+}
+
+impl<R: Read + Seek> IndexedFasta<R> {
+    /// Reads the index from `index`, for the FASTA file in `reader`.
+    pub fn new<I: BufRead>(reader: R, index: I) -> io::Result<IndexedFasta<R>> {
+        let invalid = |line: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid FASTA index line: {}", line),
+            )
+        };
+        let mut entries = HashMap::new();
+        for line in index.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() < 5 {
+                return Err(invalid(&line));
+            }
+            let number = |i: usize| fields[i].parse::<u64>().map_err(|_| invalid(&line));
+            let entry = FaiEntry {
+                length: number(1)? as usize,
+                offset: number(2)?,
+                line_bases: number(3)? as usize,
+                line_width: number(4)? as usize,
+            };
+            if entry.line_bases == 0 && entry.length > 0 {
+                return Err(invalid(&line));
+            }
+            entries.insert(fields[0].to_string(), entry);
+        }
+        Ok(IndexedFasta {
+            reader,
+            index: entries,
+        })
+    }
+
+    /// The length of a sequence, or `None` if it is not in the index.
+    pub fn length(&self, name: &str) -> Option<usize> {
+        self.index.get(name).map(|entry| entry.length)
+    }
+
+    /// Reads part of a sequence, given as zero-indexed half-open coordinates.
+    pub fn fetch(&mut self, name: &str, range: Range<usize>) -> io::Result<Vec<u8>> {
+        let entry = *self.index.get(name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is not in the FASTA index", name),
+            )
+        })?;
+        if range.start > range.end || range.end > entry.length {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{}..{} is outside {}, which is {} bp long",
+                    range.start, range.end, name, entry.length
+                ),
+            ));
+        }
+        if range.is_empty() {
+            return Ok(vec![]);
+        }
+        let position = |base: usize| {
+            entry.offset
+                + (base / entry.line_bases * entry.line_width + base % entry.line_bases) as u64
+        };
+        let start = position(range.start);
+        let end = position(range.end - 1) + 1;
+        let mut raw = vec![0; (end - start) as usize];
+        self.reader.seek(SeekFrom::Start(start))?;
+        self.reader.read_exact(&mut raw)?;
+        raw.retain(|b| !b.is_ascii_whitespace());
+        Ok(raw)
+    }
+}
+
+#[cfg(feature = "gzip")]
+fn gunzip<R: Read + 'static>(reader: R) -> io::Result<Box<dyn BufRead>> {
+    Ok(Box::new(BufReader::new(flate2::read::MultiGzDecoder::new(
+        reader,
+    ))))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gunzip<R: Read + 'static>(_: R) -> io::Result<Box<dyn BufRead>> {
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "reading gzipped FASTA requires the `gzip` feature",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fasta_reader() {
+        let input = "\n>phage_1 Escherichia phage T4\nACGT\nacgt\r\n>phage_2\n\n>phage_3\nNNNN";
+        let records: Vec<FastaRecord> = FastaReader::new(input.as_bytes())
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].id(), "phage_1");
+        assert_eq!(records[0].description(), Some("Escherichia phage T4"));
+        assert_eq!(records[0].sequence, b"ACGTacgt");
+        assert_eq!(records[1].header, "phage_2");
+        assert!(records[1].sequence.is_empty());
+        assert_eq!(records[2].sequence, b"NNNN");
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_open_gzipped() {
+        use std::io::Write;
+        let path = std::env::temp_dir().join("minced_parser_test_open_gzipped.fa.gz");
+        let mut encoder =
+            flate2::write::GzEncoder::new(File::create(&path).unwrap(), Default::default());
+        encoder.write_all(b">phage_1\nACGT\nACGT\n").unwrap();
+        encoder.finish().unwrap();
+        let records: Vec<FastaRecord> = FastaReader::open(&path)
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].sequence, b"ACGTACGT");
+    }
+
+    #[test]
+    fn test_indexed_fasta() {
+        let fasta = ">chr1 first\nACGTA\nCCGTT\nGG\n>chr2\r\nTTTT\r\nAA\r\n";
+        let index = "chr1\t12\t12\t5\t6\nchr2\t6\t34\t4\t6\n";
+        let mut genome = IndexedFasta::new(io::Cursor::new(fasta), index.as_bytes()).unwrap();
+        assert_eq!(genome.length("chr1"), Some(12));
+        assert_eq!(genome.fetch("chr1", 0..12).unwrap(), b"ACGTACCGTTGG");
+        assert_eq!(genome.fetch("chr1", 3..7).unwrap(), b"TACC");
+        assert_eq!(genome.fetch("chr1", 4..5).unwrap(), b"A");
+        assert_eq!(genome.fetch("chr1", 5..5).unwrap(), b"");
+        assert_eq!(genome.fetch("chr2", 2..6).unwrap(), b"TTAA");
+        assert!(genome.fetch("chr1", 10..13).is_err());
+        assert!(genome.fetch("chr3", 0..1).is_err());
+    }
+
+    #[test]
+    fn test_fasta_reader_rejects_headerless_sequence() {
+        let mut reader = FastaReader::new("ACGT\n>x\nACGT\n".as_bytes());
+        assert!(reader.next().unwrap().is_err());
+    }
+}
@@ -0,0 +1,312 @@
+//! Finds the genes near each array using a GFF3 annotation of the same contigs, to tell arrays
+//! with nearby cas genes apart from orphan arrays.
+
+use crate::{Array, Contig, Strand};
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+
+/// Legacy gene name prefixes used for cas genes, such as `csn2` or `cmr4`, matched
+/// case-insensitively. Non-Cas genes share many of them (`csdA`, `cscB`), so each only counts
+/// when followed by a digit.
+const LEGACY_CAS_PREFIXES: [&str; 14] = [
+    "csn", "csm", "cmr", "csy", "cse", "csx", "csa", "csb", "csc", "csd", "csf", "cpf", "c2c",
+    "cmx",
+];
+
+#[derive(Clone, Debug, PartialEq)]
+/// A single feature from a GFF3 file.
+pub struct GffFeature {
+    /// The ID of the sequence the feature is on, which should match [`Contig::id`].
+    pub seqid: String,
+    /// The type of the feature, such as `gene` or `CDS`.
+    pub feature_type: String,
+    /// Zero-indexed inclusive start coordinate.
+    pub start: usize,
+    /// Zero-indexed exclusive end coordinate.
+    pub end: usize,
+    /// The strand the feature is on, if it is stranded.
+    pub strand: Option<Strand>,
+    /// The `Name` attribute, or the `gene` attribute if there is no name.
+    pub name: Option<String>,
+    /// The `product` attribute.
+    pub product: Option<String>,
+    /// The `locus_tag` attribute, or the `ID` attribute if there is no locus tag.
+    pub locus_tag: Option<String>,
+}
+
+impl GffFeature {
+    /// Whether the feature looks like a cas gene, either because its name is a cas gene name
+    /// (`cas` followed by anything, as in `cas9` or `casA`, or a legacy prefix such as `csn` or
+    /// `cmr` followed by a digit) or because its product mentions CRISPR or a Cas protein.
+    pub fn is_cas(&self) -> bool {
+        let name_matches = self.name.as_deref().is_some_and(|name| {
+            let name = name.to_ascii_lowercase();
+            name.starts_with("cas")
+                || LEGACY_CAS_PREFIXES.iter().any(|p| {
+                    name.strip_prefix(p)
+                        .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+                })
+        });
+        let product_matches = self.product.as_deref().is_some_and(|product| {
+            let product = product.to_ascii_lowercase();
+            product.contains("crispr")
+                || product
+                    .split(|c: char| !c.is_ascii_alphanumeric())
+                    .any(|word| {
+                        word.strip_prefix("cas")
+                            .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+                    })
+        });
+        name_matches || product_matches
+    }
+}
+
+/// Reads the features in a GFF3 file, stopping at the `##FASTA` section if there is one.
+pub fn read_gff3<R: BufRead>(reader: R) -> io::Result<Vec<GffFeature>> {
+    let mut features = vec![];
+    for line in reader.lines() {
+        let line = line?;
+        if line.starts_with("##FASTA") {
+            break;
+        }
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        features.push(parse_gff3_line(&line).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid GFF3 line: {}", line),
+            )
+        })?);
+    }
+    Ok(features)
+}
+
+/// Parses one feature line of a GFF3 file.
+fn parse_gff3_line(line: &str) -> Option<GffFeature> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() != 9 {
+        return None;
+    }
+    let start = fields[3].parse::<usize>().ok()?.checked_sub(1)?;
+    let end = fields[4].parse::<usize>().ok()?;
+    let strand = match fields[6] {
+        "+" => Some(Strand::Forward),
+        "-" => Some(Strand::Reverse),
+        _ => None,
+    };
+    let attributes: HashMap<&str, String> = fields[8]
+        .split(';')
+        .filter_map(|attribute| attribute.split_once('='))
+        .map(|(key, value)| (key.trim(), percent_decode(value)))
+        .collect();
+    let attribute = |keys: &[&str]| keys.iter().find_map(|k| attributes.get(k).cloned());
+    Some(GffFeature {
+        seqid: percent_decode(fields[0]),
+        feature_type: fields[2].to_string(),
+        start,
+        end,
+        strand,
+        name: attribute(&["Name", "gene"]),
+        product: attribute(&["product"]),
+        locus_tag: attribute(&["locus_tag", "ID"]),
+    })
+}
+
+/// Decodes the `%XX` escapes GFF3 uses for reserved characters.
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Where a feature is relative to an array, in genomic coordinates.
+pub enum Side {
+    /// The feature ends before the array starts.
+    Before,
+    /// The feature overlaps the array.
+    Overlapping,
+    /// The feature starts after the array ends.
+    After,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A feature near an array.
+pub struct Neighbour<'g> {
+    /// The feature.
+    pub feature: &'g GffFeature,
+    /// Which side of the array the feature is on.
+    pub side: Side,
+    /// The number of bases between the feature and the array, which is zero if they overlap.
+    pub distance: usize,
+    /// Whether the feature looks like a cas gene (see [`GffFeature::is_cas`]).
+    pub is_cas: bool,
+}
+
+/// The features of a GFF3 annotation, grouped by the sequence they are on.
+pub struct GeneAnnotation {
+    features: HashMap<String, Vec<GffFeature>>,
+}
+
+impl GeneAnnotation {
+    /// Groups features by sequence, keeping only those of the given types, such as `CDS`.
+    /// With no types, every feature is kept.
+    pub fn new(features: Vec<GffFeature>, feature_types: &[&str]) -> GeneAnnotation {
+        let mut grouped: HashMap<String, Vec<GffFeature>> = HashMap::new();
+        for feature in features {
+            if feature_types.is_empty() || feature_types.contains(&feature.feature_type.as_str()) {
+                grouped
+                    .entry(feature.seqid.clone())
+                    .or_default()
+                    .push(feature);
+            }
+        }
+        for features in grouped.values_mut() {
+            features.sort_by_key(|f| (f.start, f.end));
+        }
+        GeneAnnotation { features: grouped }
+    }
+
+    /// Finds the features within `distance` bp of an array, ordered by position. Features are
+    /// looked up by [`Contig::id`].
+    pub fn neighbourhood(
+        &self,
+        contig: &Contig,
+        array: &Array,
+        distance: usize,
+    ) -> Vec<Neighbour<'_>> {
+        let features = match self.features.get(contig.id()) {
+            Some(features) => features,
+            None => return vec![],
+        };
+        features
+            .iter()
+            .filter_map(|feature| {
+                let (side, gap) = if feature.end <= array.start {
+                    (Side::Before, array.start - feature.end)
+                } else if feature.start >= array.end {
+                    (Side::After, feature.start - array.end)
+                } else {
+                    (Side::Overlapping, 0)
+                };
+                (gap <= distance).then(|| Neighbour {
+                    feature,
+                    side,
+                    distance: gap,
+                    is_cas: feature.is_cas(),
+                })
+            })
+            .collect()
+    }
+
+    /// Whether any feature within `distance` bp of an array looks like a cas gene. Arrays
+    /// without one are likely orphans.
+    pub fn has_cas_neighbour(&self, contig: &Contig, array: &Array, distance: usize) -> bool {
+        self.neighbourhood(contig, array, distance)
+            .iter()
+            .any(|n| n.is_cas)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    const GFF: &str = "##gff-version 3
+MGYG000166779_38\tProkka\tgene\t8000\t9000\t.\t+\t.\tID=g1;Name=cas2
+MGYG000166779_38\tProkka\tCDS\t8000\t9000\t.\t+\t0\tID=c1;Name=cas2;product=CRISPR-associated endoribonuclease Cas2
+MGYG000166779_38\tProkka\tCDS\t9001\t10600\t.\t+\t0\tID=c2;locus_tag=L2;product=Cas9%3B type II
+MGYG000166779_38\tProkka\tCDS\t10700\t10900\t.\t-\t0\tID=c3;product=overlaps the array
+MGYG000166779_38\tProkka\tCDS\t11500\t12000\t.\t-\t0\tID=c4;gene=lacZ;product=beta-galactosidase
+MGYG000166779_43\tProkka\tCDS\t1\t100\t.\t+\t0\tID=c5;product=casein kinase
+##FASTA
+>MGYG000166779_38
+ACGT
+";
+
+    #[test]
+    fn test_read_gff3() {
+        let features = read_gff3(GFF.as_bytes()).unwrap();
+        assert_eq!(features.len(), 6);
+        assert_eq!(features[2].start, 9000);
+        assert_eq!(features[2].end, 10600);
+        assert_eq!(features[2].product.as_deref(), Some("Cas9; type II"));
+        assert_eq!(features[2].locus_tag.as_deref(), Some("L2"));
+        assert_eq!(features[4].name.as_deref(), Some("lacZ"));
+        assert_eq!(features[4].strand, Some(Strand::Reverse));
+        assert!(read_gff3("a\tb\tc\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_is_cas() {
+        let features = read_gff3(GFF.as_bytes()).unwrap();
+        let cas: Vec<bool> = features.iter().map(|f| f.is_cas()).collect();
+        assert_eq!(cas, vec![true, true, true, false, false, false]);
+
+        let named = |name: &str, product: Option<&str>| GffFeature {
+            name: Some(name.to_string()),
+            product: product.map(str::to_string),
+            ..features[0].clone()
+        };
+        assert!(named("csn2", None).is_cas());
+        assert!(named("Cmr4", None).is_cas());
+        assert!(named("casA", None).is_cas());
+        assert!(!named("csdA", Some("cysteine desulfurase")).is_cas());
+        assert!(!named("cscA", Some("sucrose-6-phosphate hydrolase")).is_cas());
+        assert!(!named("csbD", None).is_cas());
+    }
+
+    #[test]
+    fn test_neighbourhood() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let contigs = parse(&input).unwrap();
+        let annotation = GeneAnnotation::new(read_gff3(GFF.as_bytes()).unwrap(), &["CDS"]);
+
+        // The first array spans 10647..10814.
+        let (contig, array) = (&contigs[0], &contigs[0].arrays[0]);
+        let neighbours = annotation.neighbourhood(contig, array, 1000);
+        let summary: Vec<(&str, Side, usize, bool)> = neighbours
+            .iter()
+            .map(|n| {
+                (
+                    n.feature.locus_tag.as_deref().unwrap(),
+                    n.side,
+                    n.distance,
+                    n.is_cas,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("L2", Side::Before, 47, true),
+                ("c3", Side::Overlapping, 0, false),
+                ("c4", Side::After, 685, false),
+            ]
+        );
+        assert!(annotation.has_cas_neighbour(contig, array, 100));
+        assert!(!annotation.has_cas_neighbour(contig, array, 10));
+
+        let (contig, array) = (&contigs[1], &contigs[1].arrays[0]);
+        assert!(!annotation.has_cas_neighbour(contig, array, 10_000));
+    }
+}
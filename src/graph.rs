@@ -0,0 +1,389 @@
+//! Links arrays that share spacers, which suggests common ancestry.
+
+use crate::escape::{escape_dot, escape_xml};
+use crate::seeds::SeedIndex;
+use crate::sequence::{hamming_distance, reverse_complement};
+use crate::{ArrayId, Contig, Repeat};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+#[derive(Clone, Debug, PartialEq, Default)]
+/// Settings for building a [`SpacerGraph`].
+pub struct GraphOptions {
+    /// The largest number of mismatches at which two spacers count as shared. With zero, only
+    /// identical spacers are shared.
+    pub max_mismatches: usize,
+    /// Whether a spacer and its reverse complement count as the same spacer.
+    pub reverse_complement: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A spacer found in both arrays of an edge.
+pub struct SharedSpacer {
+    /// The index of the spacer's unit in the source array's
+    /// [`repeat_spacers`](crate::Array::repeat_spacers).
+    pub source_index: usize,
+    /// The index of the spacer's unit in the target array's
+    /// [`repeat_spacers`](crate::Array::repeat_spacers).
+    pub target_index: usize,
+    /// The number of mismatches between the two spacers.
+    pub mismatches: usize,
+    /// Whether one spacer matches the reverse complement of the other.
+    pub reverse: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Two arrays that share at least one spacer.
+pub struct SpacerEdge<'a> {
+    /// The array that comes first in the input.
+    pub source: ArrayId<'a>,
+    /// The array that comes later in the input.
+    pub target: ArrayId<'a>,
+    /// The shared spacers, ordered by their position in the source and then the target array.
+    pub shared: Vec<SharedSpacer>,
+}
+
+impl<'a> SpacerEdge<'a> {
+    /// The number of shared spacer pairs.
+    pub fn weight(&self) -> usize {
+        self.shared.len()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A graph with a node for each array and an edge between each pair of arrays that share spacers.
+pub struct SpacerGraph<'a> {
+    nodes: Vec<ArrayId<'a>>,
+    edges: Vec<SpacerEdge<'a>>,
+    /// The positions in `nodes` of each edge's source and target, which are used as their IDs
+    /// in exported graphs. Arrays with the same [`ArrayId`] are still separate nodes.
+    endpoints: Vec<(usize, usize)>,
+}
+
+impl<'a> SpacerGraph<'a> {
+    /// Finds the spacers shared between every pair of arrays in `contigs`. Spacers are compared
+    /// case-insensitively, and spacers within the same array are not compared with each other.
+    pub fn new(contigs: &[Contig<'a>], options: &GraphOptions) -> SpacerGraph<'a> {
+        let nodes: Vec<ArrayId<'a>> = contigs
+            .iter()
+            .flat_map(|c| c.arrays.iter().map(move |a| ArrayId::new(c, a)))
+            .collect();
+        // Each spacer, with the node of its array and its index in that array.
+        let spacers: Vec<(usize, usize, String)> = contigs
+            .iter()
+            .flat_map(|c| c.arrays.iter())
+            .enumerate()
+            .flat_map(|(node, array)| {
+                array
+                    .repeat_spacers
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(index, repeat)| match repeat {
+                        Repeat::WithSpacer(rs) => {
+                            Some((node, index, rs.spacer.to_ascii_uppercase()))
+                        }
+                        Repeat::WithoutSpacer(_) => None,
+                    })
+            })
+            .collect();
+
+        let mut index = SeedIndex::new(options.max_mismatches);
+        for (id, (_, _, spacer)) in spacers.iter().enumerate() {
+            index.insert(id, spacer);
+        }
+
+        let mut edges: BTreeMap<(usize, usize), Vec<SharedSpacer>> = BTreeMap::new();
+        for (id, (node, spacer_index, spacer)) in spacers.iter().enumerate() {
+            let reverse = options
+                .reverse_complement
+                .then(|| reverse_complement(spacer));
+            let mut matches: HashMap<usize, (usize, bool)> = HashMap::new();
+            for (query, is_reverse) in [(Some(spacer), false), (reverse.as_ref(), true)] {
+                let query = match query {
+                    Some(query) => query,
+                    None => continue,
+                };
+                for candidate in index.candidates(query) {
+                    // Each pair is found from both ends, so only keep it from the earlier one.
+                    if candidate <= id || spacers[candidate].0 == *node {
+                        continue;
+                    }
+                    let mismatches = hamming_distance(query, &spacers[candidate].2);
+                    if mismatches <= options.max_mismatches
+                        && matches.get(&candidate).is_none_or(|&(m, _)| mismatches < m)
+                    {
+                        matches.insert(candidate, (mismatches, is_reverse));
+                    }
+                }
+            }
+            for (candidate, (mismatches, reverse)) in matches {
+                let (target, target_index, _) = spacers[candidate];
+                edges
+                    .entry((*node, target))
+                    .or_default()
+                    .push(SharedSpacer {
+                        source_index: *spacer_index,
+                        target_index,
+                        mismatches,
+                        reverse,
+                    });
+            }
+        }
+
+        let endpoints = edges.keys().copied().collect();
+        let edges = edges
+            .into_iter()
+            .map(|((source, target), mut shared)| {
+                shared.sort_by_key(|s| (s.source_index, s.target_index));
+                SpacerEdge {
+                    source: nodes[source],
+                    target: nodes[target],
+                    shared,
+                }
+            })
+            .collect();
+        SpacerGraph {
+            nodes,
+            edges,
+            endpoints,
+        }
+    }
+
+    /// Every array, in the order they appear in the input.
+    pub fn nodes(&self) -> &[ArrayId<'a>] {
+        &self.nodes
+    }
+
+    /// Every pair of arrays that share at least one spacer, ordered by source and then target.
+    pub fn edges(&self) -> &[SpacerEdge<'a>] {
+        &self.edges
+    }
+
+    /// Writes the graph in Graphviz DOT format.
+    pub fn write_dot<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "graph spacers {{")?;
+        for (i, node) in self.nodes.iter().enumerate() {
+            writeln!(writer, "  n{} [label=\"{}\"];", i, escape_dot(&label(node)))?;
+        }
+        for (edge, (source, target)) in self.edges.iter().zip(&self.endpoints) {
+            writeln!(
+                writer,
+                "  n{} -- n{} [weight={}, label=\"{}\"];",
+                source,
+                target,
+                edge.weight(),
+                edge.weight()
+            )?;
+        }
+        writeln!(writer, "}}")
+    }
+
+    /// Writes the graph in GraphML format. Each edge has its weight and the positions of the
+    /// shared spacers, as `source_index:target_index` pairs separated by commas.
+    pub fn write_graphml<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        writeln!(
+            writer,
+            r#"  <key id="label" for="node" attr.name="label" attr.type="string"/>"#
+        )?;
+        writeln!(
+            writer,
+            r#"  <key id="accession" for="node" attr.name="accession" attr.type="string"/>"#
+        )?;
+        writeln!(
+            writer,
+            r#"  <key id="order" for="node" attr.name="order" attr.type="int"/>"#
+        )?;
+        writeln!(
+            writer,
+            r#"  <key id="weight" for="edge" attr.name="weight" attr.type="int"/>"#
+        )?;
+        writeln!(
+            writer,
+            r#"  <key id="positions" for="edge" attr.name="positions" attr.type="string"/>"#
+        )?;
+        writeln!(writer, r#"  <graph id="spacers" edgedefault="undirected">"#)?;
+        for (i, node) in self.nodes.iter().enumerate() {
+            writeln!(writer, r#"    <node id="n{}">"#, i)?;
+            writeln!(
+                writer,
+                r#"      <data key="label">{}</data>"#,
+                escape_xml(&label(node))
+            )?;
+            writeln!(
+                writer,
+                r#"      <data key="accession">{}</data>"#,
+                escape_xml(node.accession)
+            )?;
+            writeln!(writer, r#"      <data key="order">{}</data>"#, node.order)?;
+            writeln!(writer, "    </node>")?;
+        }
+        for (edge, (source, target)) in self.edges.iter().zip(&self.endpoints) {
+            let positions: Vec<String> = edge
+                .shared
+                .iter()
+                .map(|s| format!("{}:{}", s.source_index, s.target_index))
+                .collect();
+            writeln!(
+                writer,
+                r#"    <edge source="n{}" target="n{}">"#,
+                source, target
+            )?;
+            writeln!(
+                writer,
+                r#"      <data key="weight">{}</data>"#,
+                edge.weight()
+            )?;
+            writeln!(
+                writer,
+                r#"      <data key="positions">{}</data>"#,
+                positions.join(",")
+            )?;
+            writeln!(writer, "    </edge>")?;
+        }
+        writeln!(writer, "  </graph>")?;
+        writeln!(writer, "</graphml>")
+    }
+}
+
+/// A human-readable name for an array, numbered the way MinCED numbers them.
+fn label(array: &ArrayId) -> String {
+    format!("{} CRISPR {}", array.accession, array.order + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    const INPUT: &str = "Sequence 'a' (1000 bp)
+
+CRISPR 1   Range: 1 - 80
+POSITION	REPEAT	SPACER
+--------	-----	------
+1		GTTTCAAT	AAAACCCCGGGGTTTTACGT	[ 8, 20 ]
+29		GTTTCAAT	TTTTTTTTTTCCCCCCCCCC	[ 8, 20 ]
+57		GTTTCAAT
+--------	-----	------
+Repeats: 3	Average Length: 8		Average Length: 20
+
+Time to find repeats: 1 ms
+
+
+Sequence 'b \"O'Brien\"' (1000 bp)
+
+CRISPR 2   Range: 1 - 108
+POSITION	REPEAT	SPACER
+--------	-----	------
+1		GTTTCAAT	GCGCGCATATGCGCATATGC	[ 8, 20 ]
+29		GTTTCAAT	AAAACCCCGGGGTTTTACGT	[ 8, 20 ]
+57		GTTTCAAT	TTTTTTTTTTCCCCCCCCCA	[ 8, 20 ]
+85		GTTTCAAT
+--------	-----	------
+Repeats: 4	Average Length: 8		Average Length: 20
+
+Time to find repeats: 1 ms
+
+
+Sequence 'c' (1000 bp)
+
+CRISPR 3   Range: 1 - 52
+POSITION	REPEAT	SPACER
+--------	-----	------
+1		GTTTCAAT	ACGTAAAACCCCGGGGTTTT	[ 8, 20 ]
+29		GTTTCAAT
+--------	-----	------
+Repeats: 2	Average Length: 8		Average Length: 20
+
+Time to find repeats: 1 ms
+
+
+";
+
+    fn id(accession: &str, order: usize) -> ArrayId<'_> {
+        ArrayId { accession, order }
+    }
+
+    #[test]
+    fn test_exact_matches() {
+        let contigs = parse(INPUT).unwrap();
+        let graph = SpacerGraph::new(&contigs, &GraphOptions::default());
+        assert_eq!(graph.nodes().len(), 3);
+        assert_eq!(graph.edges().len(), 1);
+        let edge = &graph.edges()[0];
+        assert_eq!(edge.source, id("a", 0));
+        assert_eq!(edge.target, id("b \"O'Brien\"", 1));
+        assert_eq!(
+            edge.shared,
+            vec![SharedSpacer {
+                source_index: 0,
+                target_index: 1,
+                mismatches: 0,
+                reverse: false
+            }]
+        );
+    }
+
+    #[test]
+    fn test_mismatches_and_reverse_complements() {
+        let contigs = parse(INPUT).unwrap();
+        let options = GraphOptions {
+            max_mismatches: 1,
+            reverse_complement: true,
+        };
+        let graph = SpacerGraph::new(&contigs, &options);
+        let weights: Vec<(usize, usize)> = graph
+            .edges()
+            .iter()
+            .map(|e| (e.source.order, e.target.order))
+            .collect();
+        assert_eq!(weights, vec![(0, 1), (0, 2), (1, 2)]);
+        assert_eq!(graph.edges()[0].weight(), 2);
+        assert_eq!(graph.edges()[0].shared[1].mismatches, 1);
+        assert!(graph.edges()[1].shared[0].reverse);
+    }
+
+    #[test]
+    fn test_exports() {
+        let contigs = parse(INPUT).unwrap();
+        let graph = SpacerGraph::new(&contigs, &GraphOptions::default());
+
+        let mut dot = vec![];
+        graph.write_dot(&mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.starts_with("graph spacers {\n"));
+        assert!(dot.contains("  n1 [label=\"b \\\"O'Brien\\\" CRISPR 2\"];\n"));
+        assert!(dot.contains("  n0 -- n1 [weight=1, label=\"1\"];\n"));
+
+        let mut graphml = vec![];
+        graph.write_graphml(&mut graphml).unwrap();
+        let graphml = String::from_utf8(graphml).unwrap();
+        assert!(graphml.contains(r#"<data key="accession">b &quot;O&apos;Brien&quot;</data>"#));
+        assert!(graphml.contains(r#"<edge source="n0" target="n1">"#));
+        assert!(graphml.contains(r#"<data key="positions">0:1</data>"#));
+        assert_eq!(graphml.matches("<node ").count(), 3);
+    }
+
+    #[test]
+    fn test_exports_duplicate_ids() {
+        // The third array has the same accession and number as the first one.
+        let input = INPUT
+            .replace("Sequence 'c'", "Sequence 'a'")
+            .replace("CRISPR 3", "CRISPR 1");
+        let contigs = parse(&input).unwrap();
+        let options = GraphOptions {
+            max_mismatches: 1,
+            reverse_complement: true,
+        };
+        let graph = SpacerGraph::new(&contigs, &options);
+        let mut dot = vec![];
+        graph.write_dot(&mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.contains("  n0 -- n2 [weight=1, label=\"1\"];\n"));
+        assert!(dot.contains("  n1 -- n2 [weight=1, label=\"1\"];\n"));
+    }
+}
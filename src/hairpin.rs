@@ -0,0 +1,166 @@
+//! Finds stem-loops in repeats. Many CRISPR subtypes have repeats that fold into a hairpin, and
+//! its stem length is one of the features used to tell them apart.
+
+use crate::Array;
+
+#[derive(Clone, Debug, PartialEq)]
+/// Limits on the hairpins [`find_hairpin`] looks for.
+pub struct HairpinOptions {
+    /// The fewest base pairs in the stem.
+    pub min_stem: usize,
+    /// The fewest unpaired bases in the loop.
+    pub min_loop: usize,
+    /// The most unpaired bases in the loop.
+    pub max_loop: usize,
+}
+
+impl Default for HairpinOptions {
+    fn default() -> HairpinOptions {
+        HairpinOptions {
+            min_stem: 4,
+            min_loop: 3,
+            max_loop: 12,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A stem-loop: an uninterrupted run of base pairs closed by a loop of unpaired bases.
+pub struct Hairpin {
+    /// The fold in dot-bracket notation, one character per base of the sequence.
+    pub structure: String,
+    /// Zero-indexed position of the first base of the stem.
+    pub start: usize,
+    /// Zero-indexed position one past the last base of the stem.
+    pub end: usize,
+    /// The number of base pairs in the stem.
+    pub stem_length: usize,
+    /// The number of unpaired bases in the loop.
+    pub loop_length: usize,
+    /// A rough measure of stability: 3 for each G-C pair, 2 for each A-U pair and 1 for each G-U
+    /// wobble pair. Higher is more stable.
+    pub score: usize,
+}
+
+/// How strongly two bases pair in RNA, treating T as U, or `None` if they do not pair.
+fn pair_score(a: u8, b: u8) -> Option<usize> {
+    match (a.to_ascii_uppercase(), b.to_ascii_uppercase()) {
+        (b'G', b'C') | (b'C', b'G') => Some(3),
+        (b'A', b'T') | (b'T', b'A') | (b'A', b'U') | (b'U', b'A') => Some(2),
+        (b'G', b'T') | (b'T', b'G') | (b'G', b'U') | (b'U', b'G') => Some(1),
+        _ => None,
+    }
+}
+
+/// Finds the most stable stem-loop in a sequence, or `None` if no stem is at least
+/// `options.min_stem` pairs long. Of hairpins with the same score, the one with the smaller
+/// loop and then the one further 5' is chosen.
+///
+/// This is a simple search for the best single stem with no bulges or internal loops rather
+/// than a full free energy minimisation, which is enough to find the short, nearly perfect
+/// stems typical of CRISPR repeats.
+pub fn find_hairpin(sequence: &str, options: &HairpinOptions) -> Option<Hairpin> {
+    let bases = sequence.as_bytes();
+    let mut best: Option<Hairpin> = None;
+    for start in 0..bases.len() {
+        for end in start + 1..=bases.len() {
+            let (i, j) = (start, end - 1);
+            // Only grow stems from their outermost pair.
+            if start > 0 && end < bases.len() && pair_score(bases[i - 1], bases[j + 1]).is_some() {
+                continue;
+            }
+            let mut stem_length = 0;
+            let mut score = 0;
+            while i + stem_length < j - stem_length
+                && (j - i + 1) - 2 * (stem_length + 1) >= options.min_loop
+            {
+                match pair_score(bases[i + stem_length], bases[j - stem_length]) {
+                    Some(s) => {
+                        score += s;
+                        stem_length += 1;
+                    }
+                    None => break,
+                }
+            }
+            let loop_length = (j - i + 1) - 2 * stem_length;
+            if stem_length < options.min_stem || loop_length > options.max_loop {
+                continue;
+            }
+            let better = best.as_ref().is_none_or(|b| {
+                score > b.score || (score == b.score && loop_length < b.loop_length)
+            });
+            if better {
+                let mut structure = vec![b'.'; bases.len()];
+                structure[start..start + stem_length].fill(b'(');
+                structure[end - stem_length..end].fill(b')');
+                best = Some(Hairpin {
+                    structure: String::from_utf8(structure).expect("dot-bracket is ASCII"),
+                    start,
+                    end,
+                    stem_length,
+                    loop_length,
+                    score,
+                });
+            }
+        }
+    }
+    best
+}
+
+impl<'a> Array<'a> {
+    /// Finds the most stable stem-loop in the consensus repeat (see [`find_hairpin`]).
+    pub fn repeat_hairpin(&self, options: &HairpinOptions) -> Option<Hairpin> {
+        find_hairpin(&self.consensus_repeat(), options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_find_hairpin() {
+        let hairpin = find_hairpin("AAGCCGCAAAACGGCTTT", &HairpinOptions::default()).unwrap();
+        assert_eq!(hairpin.structure, "((((((.....)))))).");
+        assert_eq!(hairpin.stem_length, 6);
+        assert_eq!(hairpin.loop_length, 5);
+        assert_eq!((hairpin.start, hairpin.end), (0, 17));
+        assert_eq!(hairpin.score, 16);
+    }
+
+    #[test]
+    fn test_no_hairpin() {
+        assert_eq!(
+            find_hairpin("AAAAAAAAAAAA", &HairpinOptions::default()),
+            None
+        );
+        assert_eq!(find_hairpin("", &HairpinOptions::default()), None);
+        // The only stem would need a loop shorter than three bases.
+        assert_eq!(find_hairpin("GGGGACCCC", &HairpinOptions::default()), None);
+    }
+
+    #[test]
+    fn test_repeat_hairpin() {
+        // The E. coli type I-E repeat has a seven base pair stem.
+        let input = "Sequence 'chr1' (200 bp)
+
+CRISPR 1   Range: 1 - 67
+POSITION\tREPEAT\tSPACER
+--------\t-----\t------
+1\t\tGAGTTCCCCGCGCCAGCGGGGATAAACCG\tACGTACGTAC\t[ 29, 10 ]
+40\t\tGAGTTCCCCGCGCCAGCGGGGATAAACCG
+--------\t-----\t------
+Repeats: 2\tAverage Length: 29\t\tAverage Length: 10
+
+Time to find repeats: 1 ms
+
+
+";
+        let contigs = parse(input).unwrap();
+        let hairpin = contigs[0].arrays[0]
+            .repeat_hairpin(&HairpinOptions::default())
+            .unwrap();
+        assert_eq!(hairpin.structure, "....(((((((....))))))).......");
+    }
+}
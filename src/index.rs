@@ -0,0 +1,288 @@
+//! An index for finding the arrays, repeats and spacers that overlap a region of a contig.
+
+use crate::{Array, Contig, Repeat};
+use std::collections::HashMap;
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// What an indexed interval covers.
+pub enum RegionKind {
+    /// A whole array.
+    Array,
+    /// The repeat of the repeat-spacer unit at `unit`.
+    Repeat,
+    /// The spacer of the repeat-spacer unit at `unit`.
+    Spacer,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// An array, repeat or spacer that overlaps a queried region.
+pub struct RegionHit<'i, 'a> {
+    /// The array the hit belongs to.
+    pub array: &'i Array<'a>,
+    /// Whether the hit is the whole array, a repeat or a spacer.
+    pub kind: RegionKind,
+    /// Index into [`Array::repeat_spacers`] of the repeat or spacer, or `None` for a whole array.
+    pub unit: Option<usize>,
+    /// Zero-indexed inclusive start coordinate.
+    pub start: usize,
+    /// Zero-indexed exclusive end coordinate.
+    pub end: usize,
+}
+
+/// An interval pointing at an array (by position in [`Contig::arrays`]) and one of its parts.
+#[derive(Clone, Copy)]
+struct Interval {
+    start: usize,
+    end: usize,
+    array: usize,
+    kind: RegionKind,
+    unit: Option<usize>,
+}
+
+/// Intervals sorted by start, with the largest end seen so far at each position (`max_end`).
+///
+/// An overlap query binary searches the starts for the intervals that begin before the end of
+/// the region. It then binary searches `max_end` to skip the leading intervals that, like every
+/// interval before them, end at or before the start of the region, and scans forward over the
+/// rest, keeping those that end after the start of the region. The scan also passes intervals
+/// that end before the region but start after one that reaches past it, so a single long
+/// interval can make a query O(n). Arrays, repeats and spacers on one contig rarely nest, so in
+/// practice a query only scans a few intervals besides the k it returns.
+#[derive(Default)]
+struct Intervals {
+    intervals: Vec<Interval>,
+    max_end: Vec<usize>,
+}
+
+impl Intervals {
+    fn new(mut intervals: Vec<Interval>) -> Intervals {
+        intervals.sort_by_key(|i| (i.start, i.end));
+        let max_end = intervals
+            .iter()
+            .scan(0, |max, i| {
+                *max = (*max).max(i.end);
+                Some(*max)
+            })
+            .collect();
+        Intervals { intervals, max_end }
+    }
+
+    fn overlapping(&self, region: &Range<usize>) -> impl Iterator<Item = &Interval> {
+        let last = self.intervals.partition_point(|i| i.start < region.end);
+        let first = self.max_end[..last].partition_point(|&end| end <= region.start);
+        let start = region.start;
+        self.intervals[first..last]
+            .iter()
+            .filter(move |i| i.end > start)
+    }
+}
+
+/// Arrays, repeats and spacers indexed by contig and position, for annotating variant calls,
+/// read alignments and other genomic regions with their CRISPR context.
+pub struct ArrayIndex<'a> {
+    contigs: Vec<Contig<'a>>,
+    by_accession: HashMap<&'a str, usize>,
+    arrays: Vec<Intervals>,
+    elements: Vec<Intervals>,
+}
+
+impl<'a> ArrayIndex<'a> {
+    /// Indexes the arrays of every contig. Contigs can be looked up by their full accession or
+    /// by [`Contig::id`]. A full accession takes precedence over an ID, and if several contigs
+    /// share a name, the first one is used.
+    pub fn new(contigs: Vec<Contig<'a>>) -> ArrayIndex<'a> {
+        let mut by_accession = HashMap::new();
+        for (c, contig) in contigs.iter().enumerate() {
+            by_accession.entry(contig.accession).or_insert(c);
+        }
+        for (c, contig) in contigs.iter().enumerate() {
+            by_accession.entry(contig.id()).or_insert(c);
+        }
+        let mut arrays = Vec::with_capacity(contigs.len());
+        let mut elements = Vec::with_capacity(contigs.len());
+        for contig in &contigs {
+            let mut array_intervals = vec![];
+            let mut element_intervals = vec![];
+            for (a, array) in contig.arrays.iter().enumerate() {
+                array_intervals.push(Interval {
+                    start: array.start,
+                    end: array.end,
+                    array: a,
+                    kind: RegionKind::Array,
+                    unit: None,
+                });
+                for (unit, repeat) in array.repeat_spacers.iter().enumerate() {
+                    let mut push = |start, end, kind| {
+                        element_intervals.push(Interval {
+                            start,
+                            end,
+                            array: a,
+                            kind,
+                            unit: Some(unit),
+                        })
+                    };
+                    match repeat {
+                        Repeat::WithSpacer(rs) => {
+                            push(rs.repeat_start, rs.repeat_end, RegionKind::Repeat);
+                            push(rs.spacer_start, rs.spacer_end, RegionKind::Spacer);
+                        }
+                        Repeat::WithoutSpacer(r) => push(r.start, r.end, RegionKind::Repeat),
+                    }
+                }
+            }
+            arrays.push(Intervals::new(array_intervals));
+            elements.push(Intervals::new(element_intervals));
+        }
+        ArrayIndex {
+            contigs,
+            by_accession,
+            arrays,
+            elements,
+        }
+    }
+
+    /// The indexed contigs.
+    pub fn contigs(&self) -> &[Contig<'a>] {
+        &self.contigs
+    }
+
+    /// Gives back the indexed contigs.
+    pub fn into_contigs(self) -> Vec<Contig<'a>> {
+        self.contigs
+    }
+
+    /// Finds the arrays that overlap a half-open region of a contig, ordered by start. An empty
+    /// region, such as `5..5`, overlaps nothing.
+    pub fn arrays(&self, accession: &str, region: Range<usize>) -> Vec<&Array<'a>> {
+        self.hits(&self.arrays, accession, &region)
+            .into_iter()
+            .map(|hit| hit.array)
+            .collect()
+    }
+
+    /// Finds the arrays, repeats and spacers that overlap a half-open region of a contig. Each
+    /// overlapping array is followed by its overlapping repeats and spacers in order. An empty
+    /// region, such as `5..5`, overlaps nothing.
+    pub fn query(&self, accession: &str, region: Range<usize>) -> Vec<RegionHit<'_, 'a>> {
+        let mut hits = self.hits(&self.arrays, accession, &region);
+        hits.extend(self.hits(&self.elements, accession, &region));
+        hits.sort_by_key(|hit| (hit.array.order, hit.unit, hit.start));
+        hits
+    }
+
+    fn hits(
+        &self,
+        intervals: &[Intervals],
+        accession: &str,
+        region: &Range<usize>,
+    ) -> Vec<RegionHit<'_, 'a>> {
+        let c = match self.by_accession.get(accession) {
+            Some(&c) if !region.is_empty() => c,
+            _ => return vec![],
+        };
+        intervals[c]
+            .overlapping(region)
+            .map(|i| RegionHit {
+                array: &self.contigs[c].arrays[i.array],
+                kind: i.kind,
+                unit: i.unit,
+                start: i.start,
+                end: i.end,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn index(input: &str) -> ArrayIndex<'_> {
+        ArrayIndex::new(parse(input).unwrap())
+    }
+
+    #[test]
+    fn test_arrays() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let index = index(&input);
+        let orders = |accession, region| -> Vec<usize> {
+            index
+                .arrays(accession, region)
+                .iter()
+                .map(|a| a.order)
+                .collect()
+        };
+        assert_eq!(orders("MGYG000242676_4", 0..164254), vec![2, 3]);
+        assert_eq!(orders("MGYG000242676_4", 61024..157549), vec![2]);
+        assert_eq!(
+            orders("MGYG000242676_4", 61025..157549),
+            Vec::<usize>::new()
+        );
+        assert_eq!(orders("MGYG000242676_4", 157914..157915), vec![3]);
+        assert_eq!(
+            orders("MGYG000166779_38", 10646..10647),
+            Vec::<usize>::new()
+        );
+        assert_eq!(orders("missing", 0..100), Vec::<usize>::new());
+        // An empty region overlaps nothing, even inside an array.
+        assert_eq!(orders("MGYG000242676_4", 60600..60600), Vec::<usize>::new());
+        assert!(index.query("MGYG000242676_4", 60600..60600).is_empty());
+    }
+
+    #[test]
+    fn test_query() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let index = index(&input);
+        // The first array's first unit has its repeat at 10647..10676 and its spacer at
+        // 10676..10722.
+        let hits: Vec<(RegionKind, Option<usize>, usize, usize)> = index
+            .query("MGYG000166779_38", 10670..10680)
+            .iter()
+            .map(|hit| (hit.kind, hit.unit, hit.start, hit.end))
+            .collect();
+        assert_eq!(
+            hits,
+            vec![
+                (RegionKind::Array, None, 10647, 10814),
+                (RegionKind::Repeat, Some(0), 10647, 10676),
+                (RegionKind::Spacer, Some(0), 10676, 10722),
+            ]
+        );
+        let hits = index.query("MGYG000166779_38", 10800..20000);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[1].kind, RegionKind::Repeat);
+        assert_eq!(hits[1].unit, Some(2));
+    }
+
+    #[test]
+    fn test_lookup_by_id() {
+        let input = "Sequence 'contig1 some description' (1000 bp)
+
+CRISPR 1   Range: 11 - 30
+POSITION	REPEAT				SPACER
+--------	-----------------------------	----------------------------------------
+11		AAAAA	CCCCC	[ 5, 5 ]
+21		AAAAA
+--------	-----------------------------	----------------------------------------
+Repeats: 2	Average Length: 5		Average Length: 5
+
+Time to find repeats: 1 ms
+
+
+";
+        let index = index(input);
+        assert_eq!(index.arrays("contig1", 0..1000).len(), 1);
+        assert_eq!(index.arrays("contig1 some description", 0..1000).len(), 1);
+
+        // With two contigs of the same name, the first one is used.
+        let twice = format!("{}{}", input, input.replace("CRISPR 1", "CRISPR 2"));
+        let index = self::index(&twice);
+        assert_eq!(index.arrays("contig1", 0..1000)[0].order, 0);
+        assert_eq!(
+            index.arrays("contig1 some description", 0..1000)[0].order,
+            0
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! Extracts the leader and trailer sequences on either side of an array from its genome.
+
+use crate::fasta::IndexedFasta;
+use crate::sequence::reverse_complement_bytes;
+use crate::{Array, Contig, Strand};
+use std::io::{self, Read, Seek};
+use std::ops::Range;
+
+#[derive(Clone, Debug, PartialEq)]
+/// The sequences on either side of a CRISPR array. Both are given in the direction the array is
+/// transcribed, so on the reverse strand they are reverse complemented.
+pub struct LeaderTrailer {
+    /// The sequence upstream of the first repeat, from which the array is transcribed.
+    pub leader: Vec<u8>,
+    /// Zero-indexed half-open coordinates of the leader on the forward strand.
+    pub leader_range: Range<usize>,
+    /// The sequence downstream of the last repeat.
+    pub trailer: Vec<u8>,
+    /// Zero-indexed half-open coordinates of the trailer on the forward strand.
+    pub trailer_range: Range<usize>,
+}
+
+/// Reads up to `leader_length` bp of leader and `trailer_length` bp of trailer for an array from
+/// the genome it was found in. Sequences are looked up by [`Contig::id`], and are cut short at
+/// the ends of the contig, using [`Contig::bp`] as its length.
+///
+/// The leader is upstream of the array and the trailer downstream, according to
+/// [`Array::strand`]. If the strand is unknown, the array is assumed to be on the forward strand,
+/// as MinCED reports it.
+pub fn leader_and_trailer<R: Read + Seek>(
+    array: &Array,
+    contig: &Contig,
+    genome: &mut IndexedFasta<R>,
+    leader_length: usize,
+    trailer_length: usize,
+) -> io::Result<LeaderTrailer> {
+    let before = |length: usize| array.start.saturating_sub(length)..array.start.min(contig.bp);
+    let after =
+        |length: usize| array.end.min(contig.bp)..array.end.saturating_add(length).min(contig.bp);
+    let reverse = array.strand == Some(Strand::Reverse);
+    let (leader_range, trailer_range) = if reverse {
+        (after(leader_length), before(trailer_length))
+    } else {
+        (before(leader_length), after(trailer_length))
+    };
+    let mut fetch = |range: &Range<usize>| -> io::Result<Vec<u8>> {
+        let sequence = genome.fetch(contig.id(), range.clone())?;
+        if reverse {
+            Ok(reverse_complement_bytes(&sequence))
+        } else {
+            Ok(sequence)
+        }
+    };
+    Ok(LeaderTrailer {
+        leader: fetch(&leader_range)?,
+        trailer: fetch(&trailer_range)?,
+        leader_range,
+        trailer_range,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    const INPUT: &str = "Sequence 'chr1 test genome' (40 bp)
+
+CRISPR 1   Range: 11 - 30
+POSITION	REPEAT	SPACER
+--------	-----	------
+11		GTTTCAAT	ACGT	[ 8, 4 ]
+23		GTTTCAAT
+--------	-----	------
+Repeats: 2	Average Length: 8		Average Length: 4
+
+Time to find repeats: 1 ms
+
+
+";
+
+    const FASTA: &str = ">chr1\nAAAAACCCCGGTTTCAATACGTGTTTCAATTGGGGTTTTA\n";
+    const INDEX: &str = "chr1\t40\t6\t40\t41\n";
+
+    #[test]
+    fn test_leader_and_trailer() {
+        let mut contigs = parse(INPUT).unwrap();
+        let mut genome = IndexedFasta::new(io::Cursor::new(FASTA), INDEX.as_bytes()).unwrap();
+        let contig = &contigs[0];
+        let flanks = leader_and_trailer(&contig.arrays[0], contig, &mut genome, 5, 15).unwrap();
+        assert_eq!(flanks.leader, b"CCCCG");
+        assert_eq!(flanks.leader_range, 5..10);
+        // The trailer stops at the end of the contig.
+        assert_eq!(flanks.trailer, b"TGGGGTTTTA");
+        assert_eq!(flanks.trailer_range, 30..40);
+
+        contigs[0].arrays[0].strand = Some(Strand::Reverse);
+        let contig = &contigs[0];
+        let flanks = leader_and_trailer(&contig.arrays[0], contig, &mut genome, 5, 15).unwrap();
+        assert_eq!(flanks.leader, b"CCCCA");
+        assert_eq!(flanks.leader_range, 30..35);
+        assert_eq!(flanks.trailer, b"CGGGGTTTTT");
+        assert_eq!(flanks.trailer_range, 0..10);
+    }
+}
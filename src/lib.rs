@@ -1,4 +1,5 @@
-#![forbid(unsafe_code)]
+#![cfg_attr(not(feature = "mmap"), forbid(unsafe_code))]
+#![cfg_attr(feature = "mmap", deny(unsafe_code))]
 #![deny(warnings, missing_docs)]
 //! Parses the output produced by MinCED (<https://github.com/ctSkennerton/minced>), a CRISPR array
 //! annotation tool.
@@ -19,16 +20,84 @@
 //!     println!("{} has {} arrays", contig.accession, contig.arrays.len());
 //! }
 //! ```
+//!
+//! ## Optional features
+//!
+//! - `mmap`: `MmapReport` parses a memory-mapped report without copying it into memory first.
+//! - `parallel`: `parse_parallel` parses the contigs of a large report on all available threads.
+//! - `gzip`: [`FastaReader::open`] reads gzipped FASTA files as well as plain ones.
 
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_until},
+    bytes::complete::tag,
     character::complete::{alpha1, char, digit1, line_ending, multispace1, not_line_ending},
-    error::Error,
+    error::{Error, ErrorKind},
     multi::{many0, many1},
     sequence::{pair, tuple},
     Err, IResult,
 };
+use std::ops::Range;
+
+mod align;
+mod bytes;
+mod catalog;
+mod cluster;
+mod complexity;
+mod consensus;
+mod degenerate;
+mod escape;
+mod families;
+mod fasta;
+mod genes;
+mod graph;
+mod hairpin;
+mod index;
+mod leader;
+mod merge;
+#[cfg(feature = "mmap")]
+mod mmap;
+
+mod orientation;
+mod owned;
+mod pam;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod protospacer;
+mod quality;
+mod seeds;
+mod sequence;
+mod subtype;
+mod summary;
+mod validate;
+
+pub use align::{align_spacers, AlignedSpacer, SpacerAlignment, SpacerAlignmentKind};
+pub use bytes::{parse_bytes, ByteContig};
+pub use catalog::{CatalogOptions, Distance, SpacerCatalog, SpacerCluster, SpacerOccurrence};
+pub use complexity::{
+    spacer_complexity, write_complexity_json, write_complexity_tsv, ComplexityOptions,
+    SpacerComplexity,
+};
+pub use degenerate::DegenerateRepeat;
+pub use families::{FamilyMember, FamilyOptions, RepeatFamilies, RepeatFamily};
+pub use fasta::{FastaReader, FastaRecord, IndexedFasta};
+pub use genes::{read_gff3, GeneAnnotation, GffFeature, Neighbour, Side};
+pub use graph::{GraphOptions, SharedSpacer, SpacerEdge, SpacerGraph};
+pub use hairpin::{find_hairpin, Hairpin, HairpinOptions};
+pub use index::{ArrayIndex, RegionHit, RegionKind};
+pub use leader::{leader_and_trailer, LeaderTrailer};
+pub use merge::{find_merges, ArrayMerge, Junction, MergeOptions};
+#[cfg(feature = "mmap")]
+pub use mmap::MmapReport;
+pub use orientation::{predict_orientation, Orientation};
+pub use owned::{OwnedArray, OwnedRepeat, OwnedRepeatOnly, OwnedRepeatSpacer};
+pub use pam::{extract_flanks, Flanks, PamModel, PamSide, PositionFrequencyMatrix};
+#[cfg(feature = "parallel")]
+pub use parallel::parse_parallel;
+pub use protospacer::{ProtospacerHit, ProtospacerSearch};
+pub use quality::{ArrayQuality, EvidenceLevel, QualityFeatures, QualityFlag, QualityOptions};
+pub use subtype::{ReferenceRepeat, SubtypeClassifier, SubtypeMatch};
+pub use summary::{Distribution, Summary};
+pub use validate::{validate, Element, ValidationIssue};
 
 #[derive(Debug, PartialEq)]
 /// A single repeat and spacer.
@@ -71,6 +140,40 @@ pub enum Repeat<'a> {
     WithoutSpacer(RepeatOnly<'a>),
 }
 
+impl<'a> Repeat<'a> {
+    /// Sequence of the repeat.
+    pub fn repeat(&self) -> &'a str {
+        match self {
+            Repeat::WithSpacer(rs) => rs.repeat,
+            Repeat::WithoutSpacer(r) => r.repeat,
+        }
+    }
+
+    /// Sequence of the spacer, if there is one.
+    pub fn spacer(&self) -> Option<&'a str> {
+        match self {
+            Repeat::WithSpacer(rs) => Some(rs.spacer),
+            Repeat::WithoutSpacer(_) => None,
+        }
+    }
+
+    /// Zero-indexed inclusive start coordinate.
+    pub fn start(&self) -> usize {
+        match self {
+            Repeat::WithSpacer(rs) => rs.start,
+            Repeat::WithoutSpacer(r) => r.start,
+        }
+    }
+
+    /// Zero-indexed exclusive end coordinate.
+    pub fn end(&self) -> usize {
+        match self {
+            Repeat::WithSpacer(rs) => rs.end,
+            Repeat::WithoutSpacer(r) => r.end,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 /// A single CRISPR array.
 pub struct Array<'a> {
@@ -82,9 +185,50 @@ pub struct Array<'a> {
     pub end: usize,
     /// All of the repeat-spacer pairs in this CRISPR array.
     pub repeat_spacers: Vec<Repeat<'a>>,
+    /// The strand the array is transcribed from, if known. MinCED always reports arrays on the
+    /// forward strand and does not predict this, so the parser leaves it as `None`. See
+    /// [`predict_orientation`].
+    pub strand: Option<Strand>,
+}
+
+/// Identifies a single CRISPR array in a collection of contigs/genomes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ArrayId<'a> {
+    /// Accession of the contig/genome the array is in.
+    pub accession: &'a str,
+    /// The order of the array (see [`Array::order`]).
+    pub order: usize,
 }
 
-#[derive(Debug)]
+impl<'a> ArrayId<'a> {
+    /// Identifies `array`, which must be one of `contig`'s arrays.
+    pub fn new(contig: &Contig<'a>, array: &Array) -> ArrayId<'a> {
+        ArrayId {
+            accession: contig.accession,
+            order: array.order,
+        }
+    }
+}
+
+/// A strand of the contig/genome.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Strand {
+    /// The strand MinCED reports sequences on.
+    Forward,
+    /// The opposite strand.
+    Reverse,
+}
+
+impl std::fmt::Display for Strand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Strand::Forward => write!(f, "+"),
+            Strand::Reverse => write!(f, "-"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
 /// Represents all of the CRISPR arrays in a single contig or genome.
 pub struct Contig<'a> {
     /// Accession of the contig/genome.
@@ -95,8 +239,34 @@ pub struct Contig<'a> {
     pub arrays: Vec<Array<'a>>,
 }
 
+impl<'a> Contig<'a> {
+    /// The sequence ID, i.e. the first whitespace-delimited word of the FASTA header. MinCED
+    /// reports the whole header as the accession, so this is what matches the assembly.
+    pub fn id(&self) -> &'a str {
+        split_header(self.accession).0
+    }
+
+    /// The rest of the FASTA header after the ID, or `None` if the header has no description.
+    pub fn description(&self) -> Option<&'a str> {
+        split_header(self.accession).1
+    }
+}
+
+/// Splits a FASTA header into its ID and description. Only ASCII whitespace separates them, as
+/// in [`ByteContig`](crate::ByteContig), so both parsers agree on where the ID ends.
+pub(crate) fn split_header(header: &str) -> (&str, Option<&str>) {
+    let header = header.trim_ascii_start();
+    match header.find(|c: char| c.is_ascii_whitespace()) {
+        Some(end) => {
+            let description = header[end..].trim_ascii();
+            (&header[..end], Some(description).filter(|d| !d.is_empty()))
+        }
+        None => (header, None),
+    }
+}
+
 /// Parses the output of minCED for a single contig/genome.
-pub fn parse(input: &str) -> Result<Vec<Contig>, Err<Error<&str>>> {
+pub fn parse(input: &str) -> Result<Vec<Contig<'_>>, Err<Error<&str>>> {
     let result = many0(parse_contig_arrays)(input);
     match result {
         Ok((_, contigs)) => Ok(contigs),
@@ -104,16 +274,37 @@ pub fn parse(input: &str) -> Result<Vec<Contig>, Err<Error<&str>>> {
     }
 }
 
+/// Finds the byte range of each contig's record, that is, everything from one `Sequence '` line
+/// up to the next one (or the end of the input).
+///
+/// Like [`parse`], this finds nothing if the input does not start with a `Sequence '` line. A
+/// record whose contig does not use all of it ends the report, as far as [`parse`] is
+/// concerned, so callers should stop after it.
+pub(crate) fn record_ranges(input: &[u8]) -> Vec<Range<usize>> {
+    let mut starts = vec![];
+    let mut position = 0;
+    for line in input.split_inclusive(|&b| b == b'\n') {
+        if line.starts_with(b"Sequence '") {
+            starts.push(position);
+        }
+        position += line.len();
+    }
+    if starts.first() != Some(&0) {
+        return vec![];
+    }
+    let mut ranges = Vec::with_capacity(starts.len());
+    for (i, &start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).copied().unwrap_or(input.len());
+        ranges.push(start..end);
+    }
+    ranges
+}
+
 /// Parses the accession and arrays for a single contig/genome
-fn parse_contig_arrays(input: &str) -> IResult<&str, Contig> {
-    let result = tuple((
-        parse_accession_line,
-        skip_empty_line,
-        many1(parse_array),
-        parse_footer,
-    ))(input);
+pub(crate) fn parse_contig_arrays(input: &str) -> IResult<&str, Contig<'_>> {
+    let result = pair(parse_accession_line, parse_arrays)(input);
     match result {
-        Ok((remainder, ((accession, bp), _, arrays, _))) => Ok((
+        Ok((remainder, ((accession, bp), arrays))) => Ok((
             remainder,
             Contig {
                 accession,
@@ -125,8 +316,17 @@ fn parse_contig_arrays(input: &str) -> IResult<&str, Contig> {
     }
 }
 
+/// Parses everything that follows the accession line of a contig/genome.
+pub(crate) fn parse_arrays(input: &str) -> IResult<&str, Vec<Array<'_>>> {
+    let result = tuple((skip_empty_line, many1(parse_array), parse_footer))(input);
+    match result {
+        Ok((remainder, (_, arrays, _))) => Ok((remainder, arrays)),
+        Err(e) => Err(e),
+    }
+}
+
 /// Parses a single CRISPR array.
-fn parse_array(input: &str) -> IResult<&str, Array> {
+fn parse_array(input: &str) -> IResult<&str, Array<'_>> {
     let result = tuple((
         skip_empty_line,
         parse_crispr_order_and_coordinates,
@@ -145,6 +345,7 @@ fn parse_array(input: &str) -> IResult<&str, Array> {
                 start,
                 end,
                 repeat_spacers,
+                strand: None,
             },
         )),
         Err(e) => Err(e),
@@ -210,32 +411,38 @@ fn parse_crispr_order_and_coordinates(input: &str) -> IResult<&str, (usize, usiz
     }
 }
 
-/// Parses the contig/genome accession and length
+/// Parses the contig/genome accession and length. The accession is everything between
+/// `Sequence '` and the final `' (N bp)` on the line, since MinCED copies the FASTA header through
+/// verbatim and it may itself contain quotes, spaces or parentheses.
 fn parse_accession_line(input: &str) -> IResult<&str, (&str, usize)> {
-    let result = tuple((
-        tag("Sequence '"),
-        take_until("'"),
-        tag("'"),
-        char(' '),
-        tag("("),
-        take_until(" "),
-        tag(" bp)"),
-    ))(input);
-    match result {
-        Ok((remainder, (_, accession, _, _, _, bp, _))) => {
-            Ok((remainder, (accession, bp.parse::<usize>().unwrap())))
-        }
-        Err(e) => Err(e),
+    let (remainder, (_, line)) = pair(tag("Sequence '"), not_line_ending)(input)?;
+    match split_accession_line(line.as_bytes()) {
+        Some((accession_len, bp)) => Ok((remainder, (&line[..accession_len], bp))),
+        None => Err(Err::Error(Error::new(line, ErrorKind::Tag))),
     }
 }
 
+/// Given the rest of an accession line after `Sequence '`, finds the length of the accession in
+/// bytes and the length of the contig/genome in bp.
+pub(crate) fn split_accession_line(line: &[u8]) -> Option<(usize, usize)> {
+    let line = line.strip_suffix(b" bp)")?;
+    let open = line.iter().rposition(|&b| b == b'(')?;
+    let digits = &line[open + 1..];
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    let accession = line[..open].strip_suffix(b"' ")?;
+    let bp = std::str::from_utf8(digits).ok()?.parse::<usize>().ok()?;
+    Some((accession.len(), bp))
+}
+
 /// Parses a single repeat/spacer line
-fn parse_repeat_spacer_line(input: &str) -> IResult<&str, Repeat> {
+fn parse_repeat_spacer_line(input: &str) -> IResult<&str, Repeat<'_>> {
     alt((parse_repeat_with_spacer, parse_repeat_only))(input)
 }
 
 /// Parses a repeat entry that has no spacer. This is always the final repeat in the array.
-fn parse_repeat_only(input: &str) -> IResult<&str, Repeat> {
+fn parse_repeat_only(input: &str) -> IResult<&str, Repeat<'_>> {
     let result = tuple((digit1, multispace1, alpha1, multispace1))(input);
     match result {
         Ok((remaining, (raw_start, _, repeat, _))) => {
@@ -254,7 +461,7 @@ fn parse_repeat_only(input: &str) -> IResult<&str, Repeat> {
 }
 
 /// Parses a repeat and spacer entry.
-fn parse_repeat_with_spacer(input: &str) -> IResult<&str, Repeat> {
+fn parse_repeat_with_spacer(input: &str) -> IResult<&str, Repeat<'_>> {
     let result = tuple((
         digit1,
         multispace1,
@@ -330,6 +537,7 @@ Repeats: 3      Average Length: 29              Average Length: 40\n";
                     repeat: "CAAGTGCACCAACCAATCTCACCACCTCA",
                 }),
             ],
+            strand: None,
         };
         let (_, actual) = parse_array(input).unwrap();
         assert_eq!(expected, actual);
@@ -351,6 +559,53 @@ Repeats: 3      Average Length: 29              Average Length: 40\n";
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn test_parse_accession_line_with_quotes() {
+        let input = "Sequence 'strain O'Brien contig 1' (500 bp)\n";
+        let (remainder, actual) = parse_accession_line(input).unwrap();
+        assert_eq!(("strain O'Brien contig 1", 500), actual);
+        assert_eq!("\n", remainder);
+    }
+
+    #[test]
+    fn test_parse_accession_line_with_unusual_characters() {
+        let cases = [
+            (
+                "Sequence 'NZ_CP009072.1 E. coli (strain K-12)' (4641652 bp)",
+                "NZ_CP009072.1 E. coli (strain K-12)",
+                4641652,
+            ),
+            (
+                "Sequence 'contig_1\tlength=500\tcov=12.5' (500 bp)",
+                "contig_1\tlength=500\tcov=12.5",
+                500,
+            ),
+            (
+                "Sequence 'Müller\u{2019}s isolate \u{3b1}-7' (12 bp)",
+                "Müller\u{2019}s isolate \u{3b1}-7",
+                12,
+            ),
+            ("Sequence ''quoted'' (1 bp)", "'quoted'", 1),
+            ("Sequence '' (1 bp)", "", 1),
+        ];
+        for (input, accession, bp) in cases {
+            let (_, actual) = parse_accession_line(input).unwrap();
+            assert_eq!((accession, bp), actual);
+        }
+    }
+
+    #[test]
+    fn test_parse_accession_line_malformed() {
+        for input in [
+            "Sequence 'MGYG000166779_38' (12280bp)",
+            "Sequence 'MGYG000166779_38' ( bp)",
+            "Sequence 'MGYG000166779_38 (12280 bp)",
+            "Sequence 'MGYG000166779_38' (12x80 bp)",
+        ] {
+            assert!(parse_accession_line(input).is_err());
+        }
+    }
+
     #[test]
     fn test_parse_repeat_spacer() {
         let input = "10723           CAAGTGCACCAACCAATCTCACCACCTCA   CCATCTCACCACCTCTCAGGGGGTGCAGTTGTCT      [ 29, 34 ]\n";
@@ -423,10 +678,33 @@ Time to find repeats: 22 ms
 ";
         let (_, contig) = parse_contig_arrays(input).unwrap();
         assert_eq!(contig.accession, "MGYG000242676_4");
+        assert_eq!(contig.id(), "MGYG000242676_4");
+        assert_eq!(contig.description(), None);
         assert_eq!(contig.bp, 164254);
         assert_eq!(contig.arrays.len(), 2);
     }
 
+    #[test]
+    fn test_contig_id_and_description() {
+        let contig = Contig {
+            accession: "NZ_CP009072.1 Escherichia coli ATCC 25922,\tcomplete genome ",
+            bp: 5130767,
+            arrays: vec![],
+        };
+        assert_eq!(contig.id(), "NZ_CP009072.1");
+        assert_eq!(
+            contig.description(),
+            Some("Escherichia coli ATCC 25922,\tcomplete genome")
+        );
+        assert_eq!(split_header("contig_7\t"), ("contig_7", None));
+        assert_eq!(split_header(""), ("", None));
+        // A no-break space is part of the ID, as it is when parsing bytes.
+        assert_eq!(
+            split_header("strain\u{a0}7 plasmid"),
+            ("strain\u{a0}7", Some("plasmid"))
+        );
+    }
+
     #[test]
     fn test_parse() {
         let input = "Sequence 'MGYG000166779_38' (12280 bp)
@@ -556,4 +834,20 @@ Time to find repeats: 9 ms
         let array_count: usize = contigs.iter().map(|c| c.arrays.len()).sum();
         assert_eq!(array_count, 5);
     }
+
+    #[test]
+    fn test_record_ranges() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let ranges = record_ranges(input.as_bytes());
+        assert_eq!(ranges.len(), parse(&input).unwrap().len());
+        assert_eq!(ranges[0].start, 0);
+        assert_eq!(ranges.last().unwrap().end, input.len());
+        for range in ranges {
+            let (remainder, _) = parse_contig_arrays(&input[range]).unwrap();
+            assert!(remainder.is_empty());
+        }
+        let preamble = format!("minced 0.4.2\n{}", input);
+        assert!(record_ranges(preamble.as_bytes()).is_empty());
+        assert!(parse(&preamble).unwrap().is_empty());
+    }
 }
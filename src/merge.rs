@@ -0,0 +1,266 @@
+//! Finds arrays that MinCED split in two or reported twice, and proposes the array they came
+//! from.
+
+use crate::owned::{OwnedArray, OwnedRepeat};
+use crate::{Array, ArrayId, Contig, Repeat, RepeatFamilies};
+
+#[derive(Clone, Debug, PartialEq)]
+/// Settings for finding [`ArrayMerge`]s.
+pub struct MergeOptions {
+    /// The largest number of bases between two arrays of the same repeat family at which they
+    /// are treated as one array. MinCED usually splits an array where a repeat is too mutated to
+    /// be found, so the gap is about one repeat and two spacers long.
+    pub max_gap: usize,
+}
+
+impl Default for MergeOptions {
+    fn default() -> MergeOptions {
+        MergeOptions { max_gap: 150 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// How two arrays of the same repeat family are positioned relative to each other.
+pub enum Junction {
+    /// The second array starts `gap` bases after the first one ends.
+    Gap {
+        /// Bases between the end of the first array and the start of the second.
+        gap: usize,
+    },
+    /// The second array starts `overlap` bases before the first one ends.
+    Overlap {
+        /// Bases covered by both arrays.
+        overlap: usize,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Two neighbouring arrays that are probably one array, and the array they would make together.
+pub struct ArrayMerge<'a> {
+    /// The array that starts first.
+    pub first: ArrayId<'a>,
+    /// The array that starts second.
+    pub second: ArrayId<'a>,
+    /// The repeat family both arrays belong to.
+    pub family: usize,
+    /// How the arrays are positioned relative to each other.
+    pub junction: Junction,
+    /// The merged array, which takes its order from the first array. When there is a gap
+    /// between the last repeat of the first array and the first repeat of the second, the gap
+    /// becomes a spacer of `N`s, since it most likely holds a degenerate repeat that MinCED
+    /// missed and the spacers around it. Where the arrays overlap, the repeats of the second
+    /// array that are already covered by the first are dropped.
+    pub merged: OwnedArray,
+}
+
+/// Finds pairs of neighbouring arrays on the same contig that belong to the same repeat family,
+/// in the same orientation, and that overlap or are at most `options.max_gap` bases apart.
+///
+/// A run of three or more pieces of one array is reported as a merge for each neighbouring
+/// pair.
+pub fn find_merges<'a>(
+    contigs: &[Contig<'a>],
+    families: &RepeatFamilies<'a>,
+    options: &MergeOptions,
+) -> Vec<ArrayMerge<'a>> {
+    let mut merges = vec![];
+    for contig in contigs {
+        let mut arrays: Vec<&Array> = contig.arrays.iter().collect();
+        arrays.sort_by_key(|a| (a.start, a.end));
+        for pair in arrays.windows(2) {
+            let (first, second) = (pair[0], pair[1]);
+            let (first_id, second_id) = (ArrayId::new(contig, first), ArrayId::new(contig, second));
+            let family = match (families.family(&first_id), families.family(&second_id)) {
+                (Some(a), Some(b)) if a.id == b.id => a,
+                _ => continue,
+            };
+            let is_reverse = |id: &ArrayId| {
+                family
+                    .members
+                    .iter()
+                    .find(|m| m.array == *id)
+                    .map(|m| m.reverse)
+            };
+            if is_reverse(&first_id) != is_reverse(&second_id) {
+                continue;
+            }
+            let junction = if second.start < first.end {
+                Junction::Overlap {
+                    overlap: first.end - second.start,
+                }
+            } else {
+                Junction::Gap {
+                    gap: second.start - first.end,
+                }
+            };
+            if matches!(junction, Junction::Gap { gap } if gap > options.max_gap) {
+                continue;
+            }
+            merges.push(ArrayMerge {
+                first: first_id,
+                second: second_id,
+                family: family.id,
+                junction,
+                merged: merge(first, second),
+            });
+        }
+    }
+    merges
+}
+
+/// A repeat, its start, and the spacer that follows it with the spacer's start.
+type Unit = (String, usize, Option<(String, usize)>);
+
+/// Joins two arrays, the first of which starts first.
+fn merge(first: &Array, second: &Array) -> OwnedArray {
+    let units = |array: &Array| -> Vec<Unit> {
+        array
+            .repeat_spacers
+            .iter()
+            .map(|r| match r {
+                Repeat::WithSpacer(rs) => (
+                    rs.repeat.to_string(),
+                    rs.repeat_start,
+                    Some((rs.spacer.to_string(), rs.spacer_start)),
+                ),
+                Repeat::WithoutSpacer(r) => (r.repeat.to_string(), r.start, None),
+            })
+            .collect()
+    };
+    let mut merged = units(first);
+    let first_end = merged
+        .last()
+        .map_or(first.start, |(repeat, start, _)| start + repeat.len());
+    let mut rest = units(second).into_iter().peekable();
+    // Where the arrays overlap, a spacer of the second array that follows a repeat the first
+    // array already has can still extend the first array.
+    let mut overlapping_spacer = None;
+    while let Some((_, _, spacer)) = rest.next_if(|(_, start, _)| *start < first_end) {
+        overlapping_spacer = spacer.filter(|(_, start)| *start >= first_end);
+    }
+    if let (Some((_, next_start, _)), Some((_, _, last_spacer))) = (rest.peek(), merged.last_mut())
+    {
+        // The last repeat of an array has no spacer, so the gap before the next repeat becomes
+        // one.
+        if last_spacer.is_none() {
+            *last_spacer = overlapping_spacer.or_else(|| {
+                let gap = next_start - first_end;
+                Some(("N".repeat(gap), first_end))
+            });
+        }
+    }
+    merged.extend(rest);
+    OwnedArray {
+        order: first.order,
+        start: first.start,
+        end: first.end.max(second.end),
+        repeat_spacers: merged
+            .into_iter()
+            .map(|(repeat, start, spacer)| OwnedRepeat::new(repeat, start, spacer))
+            .collect(),
+        strand: if first.strand == second.strand {
+            first.strand
+        } else {
+            None
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, FamilyOptions};
+
+    const INPUT: &str = "Sequence 'chr1' (200 bp)
+
+CRISPR 1   Range: 11 - 30
+POSITION\tREPEAT\tSPACER
+--------\t-----\t------
+11\t\tGTTTCAAT\tACGT\t[ 8, 4 ]
+23\t\tGTTTCAAT
+--------\t-----\t------
+Repeats: 2\tAverage Length: 8\t\tAverage Length: 4
+
+CRISPR 2   Range: 51 - 70
+POSITION\tREPEAT\tSPACER
+--------\t-----\t------
+51\t\tGTTTCAAT\tTTGG\t[ 8, 4 ]
+63\t\tGTTTCAAT
+--------\t-----\t------
+Repeats: 2\tAverage Length: 8\t\tAverage Length: 4
+
+CRISPR 3   Range: 63 - 82
+POSITION\tREPEAT\tSPACER
+--------\t-----\t------
+63\t\tGTTTCAAT\tCCAA\t[ 8, 4 ]
+75\t\tGTTTCAAT
+--------\t-----\t------
+Repeats: 2\tAverage Length: 8\t\tAverage Length: 4
+
+CRISPR 4   Range: 101 - 120
+POSITION\tREPEAT\tSPACER
+--------\t-----\t------
+101\t\tCCCCGGGGAAAA\tACGT\t[ 12, 4 ]
+117\t\tCCCCGGGGAAAA
+--------\t-----\t------
+Repeats: 2\tAverage Length: 12\t\tAverage Length: 4
+
+Time to find repeats: 1 ms
+
+
+";
+
+    #[test]
+    fn test_find_merges() {
+        let contigs = parse(INPUT).unwrap();
+        let families = RepeatFamilies::new(&contigs, &FamilyOptions::default());
+        let merges = find_merges(&contigs, &families, &MergeOptions::default());
+        let summary: Vec<(usize, usize, Junction)> = merges
+            .iter()
+            .map(|m| (m.first.order, m.second.order, m.junction))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (0, 1, Junction::Gap { gap: 20 }),
+                (1, 2, Junction::Overlap { overlap: 8 }),
+            ]
+        );
+
+        let merged = merges[0].merged.as_array();
+        assert_eq!((merged.start, merged.end), (10, 70));
+        let units: Vec<(&str, Option<&str>, usize)> = merged
+            .repeat_spacers
+            .iter()
+            .map(|r| (r.repeat(), r.spacer(), r.start()))
+            .collect();
+        assert_eq!(
+            units,
+            vec![
+                ("GTTTCAAT", Some("ACGT"), 10),
+                ("GTTTCAAT", Some("NNNNNNNNNNNNNNNNNNNN"), 22),
+                ("GTTTCAAT", Some("TTGG"), 50),
+                ("GTTTCAAT", None, 62),
+            ]
+        );
+
+        let merged = merges[1].merged.as_array();
+        assert_eq!((merged.start, merged.end), (50, 82));
+        let spacers: Vec<&str> = merged
+            .repeat_spacers
+            .iter()
+            .filter_map(Repeat::spacer)
+            .collect();
+        assert_eq!(spacers, vec!["TTGG", "CCAA"]);
+        assert_eq!(merged.repeat_spacers.len(), 3);
+    }
+
+    #[test]
+    fn test_max_gap() {
+        let contigs = parse(INPUT).unwrap();
+        let families = RepeatFamilies::new(&contigs, &FamilyOptions::default());
+        let merges = find_merges(&contigs, &families, &MergeOptions { max_gap: 10 });
+        assert_eq!(merges.len(), 1);
+        assert_eq!(merges[0].junction, Junction::Overlap { overlap: 8 });
+    }
+}
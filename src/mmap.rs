@@ -0,0 +1,130 @@
+//! Zero-copy access to reports stored on disk.
+
+use crate::{parse_contig_arrays, record_ranges, Contig};
+use memmap2::Mmap;
+use nom::{
+    error::{Error, ErrorKind},
+    Err,
+};
+use std::fs::File;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+/// A MinCED report that is memory-mapped rather than read into a heap buffer.
+///
+/// The contigs borrow directly from the mapping, so nothing is copied. The location of each
+/// contig's record is found when the report is opened, which allows any contig to be parsed on
+/// its own without touching the rest of the file.
+///
+/// Each record is checked to be valid UTF-8 when it is parsed, since the file could change
+/// while it is mapped.
+///
+/// As with [`parse`](crate::parse), a report that does not start with a `Sequence '` line has no
+/// contigs. Each record is parsed on its own, so unlike [`parse`](crate::parse), contigs after a
+/// record with trailing text that is not part of the contig are still found.
+pub struct MmapReport {
+    mmap: Mmap,
+    records: Vec<Range<usize>>,
+}
+
+impl MmapReport {
+    /// Maps the report at `path` into memory.
+    ///
+    /// The file must not be modified or truncated while the report is open.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<MmapReport> {
+        let file = File::open(path)?;
+        // SAFETY: the mapping is read-only, and the caller is responsible for not modifying the
+        // file while it is mapped, as documented above.
+        #[allow(unsafe_code)]
+        let mmap = unsafe { Mmap::map(&file)? };
+        let records = record_ranges(&mmap);
+        Ok(MmapReport { mmap, records })
+    }
+
+    /// The number of contigs in the report.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Whether the report contains no contigs.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Parses the nth contig in the report, or returns `None` if there are not that many. A
+    /// record that is not valid UTF-8 is an error pointing at the start of the record.
+    pub fn get(&self, index: usize) -> Option<Result<Contig<'_>, Err<Error<&str>>>> {
+        self.records
+            .get(index)
+            .map(|range| self.parse_record(range))
+    }
+
+    /// Parses each contig in the report in turn.
+    pub fn iter(&self) -> impl Iterator<Item = Result<Contig<'_>, Err<Error<&str>>>> {
+        self.records.iter().map(|range| self.parse_record(range))
+    }
+
+    fn parse_record(&self, range: &Range<usize>) -> Result<Contig<'_>, Err<Error<&str>>> {
+        match std::str::from_utf8(&self.mmap[range.clone()]) {
+            Ok(text) => parse_contig_arrays(text).map(|(_, contig)| contig),
+            Err(_) => Err(Err::Error(Error::new("", ErrorKind::Char))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_mmap_report() {
+        let report = MmapReport::open("examples/minced.txt").unwrap();
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let expected = parse(&input).unwrap();
+        assert_eq!(report.len(), expected.len());
+        let actual: Vec<Contig> = report.iter().map(|c| c.unwrap()).collect();
+        assert_eq!(expected, actual);
+        let last = report.get(report.len() - 1).unwrap().unwrap();
+        assert_eq!(&last, expected.last().unwrap());
+        assert!(report.get(report.len()).is_none());
+    }
+
+    #[test]
+    fn test_mmap_report_stray_line() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let stray = input.replacen(
+            "Sequence 'MGYG000242676_4'",
+            "stray line\nSequence 'MGYG000242676_4'",
+            1,
+        );
+        let path = std::env::temp_dir().join("minced-parser-mmap-stray.txt");
+        std::fs::write(&path, &stray).unwrap();
+        let report = MmapReport::open(&path).unwrap();
+        // Unlike parse, which stops at the stray line, every record is still parsed.
+        let contigs: Vec<Contig> = report.iter().map(|c| c.unwrap()).collect();
+        assert_eq!(parse(&stray).unwrap().len(), 2);
+        assert_eq!(contigs, parse(&input).unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_mmap_report_invalid_utf8() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let mut bytes = input
+            .replacen("MGYG000166779_43", "MGYG000166779_4?", 1)
+            .into_bytes();
+        let position = bytes.iter().position(|&b| b == b'?').unwrap();
+        bytes[position] = 0xFC;
+        let path = std::env::temp_dir().join("minced-parser-mmap-latin1.txt");
+        std::fs::write(&path, &bytes).unwrap();
+        let report = MmapReport::open(&path).unwrap();
+        let results: Vec<bool> = report.iter().map(|c| c.is_ok()).collect();
+        assert_eq!(results.len(), report.len());
+        assert!(results[0]);
+        assert!(!results[1]);
+        assert!(results[2..].iter().all(|&ok| ok));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,172 @@
+//! Predicts which strand a CRISPR array is transcribed from.
+
+use crate::{Array, Strand};
+
+/// The 3' motif of repeats that are in the transcribed orientation, as described by CRISPRDirection
+/// (Biswas et al., 2014), followed by one base of any kind.
+const THREE_PRIME_MOTIF: &str = "ATTGAAA";
+/// The reverse complement of [`THREE_PRIME_MOTIF`], preceded by one base of any kind.
+const FIVE_PRIME_MOTIF: &str = "TTTCAAT";
+
+/// How much each kind of evidence counts towards a prediction.
+const DEGENERATE_WEIGHT: f64 = 1.0;
+const MOTIF_WEIGHT: f64 = 1.0;
+const AT_RICHNESS_WEIGHT: f64 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// The predicted orientation of a CRISPR array.
+pub struct Orientation {
+    /// The strand the array is most likely transcribed from.
+    pub strand: Strand,
+    /// How strongly the evidence supports `strand`, from 0 (no evidence, so the prediction falls
+    /// back to [`Strand::Forward`]) to 1 (every feature agrees).
+    pub confidence: f64,
+}
+
+/// Predicts the strand an array is transcribed from, using features of its repeats:
+///
+/// - Repeats accumulate mutations at the trailer end, so whichever end repeat differs more from
+///   the consensus is taken to be the trailer.
+/// - Repeats in the transcribed orientation often end with `ATTGAAA(N)`. A consensus starting
+///   with the reverse complement, `(N)TTTCAAT`, suggests the array is on the reverse strand.
+/// - Mutations in the degenerate end repeat are biased towards A and T, so whichever end repeat is
+///   more AT-rich than the consensus is weakly taken to be the trailer.
+///
+/// Set [`Array::strand`] from the result to record the prediction.
+pub fn predict_orientation(array: &Array) -> Orientation {
+    let consensus = array.consensus_repeat();
+    let (first, last) = match (array.repeat_spacers.first(), array.repeat_spacers.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => {
+            return Orientation {
+                strand: Strand::Forward,
+                confidence: 0.0,
+            }
+        }
+    };
+
+    // Positive scores support the forward strand, negative scores the reverse strand.
+    let mut score = 0.0;
+
+    let (first_distance, last_distance) =
+        (first.distance_to(&consensus), last.distance_to(&consensus));
+    score += DEGENERATE_WEIGHT * sign(last_distance as f64 - first_distance as f64);
+
+    if has_three_prime_motif(&consensus) {
+        score += MOTIF_WEIGHT;
+    }
+    if has_five_prime_motif(&consensus) {
+        score -= MOTIF_WEIGHT;
+    }
+
+    let baseline = at_fraction(&consensus);
+    let first_gain = at_fraction(first.repeat()) - baseline;
+    let last_gain = at_fraction(last.repeat()) - baseline;
+    score += AT_RICHNESS_WEIGHT * sign(last_gain - first_gain);
+
+    let total = DEGENERATE_WEIGHT + MOTIF_WEIGHT + AT_RICHNESS_WEIGHT;
+    Orientation {
+        strand: if score < 0.0 {
+            Strand::Reverse
+        } else {
+            Strand::Forward
+        },
+        confidence: (score.abs() / total).min(1.0),
+    }
+}
+
+/// Whether the repeat ends with `ATTGAAA`, optionally followed by one more base.
+fn has_three_prime_motif(repeat: &str) -> bool {
+    let repeat = repeat.to_ascii_uppercase();
+    repeat.ends_with(THREE_PRIME_MOTIF)
+        || repeat[..repeat.len().saturating_sub(1)].ends_with(THREE_PRIME_MOTIF)
+}
+
+/// Whether the repeat starts with `TTTCAAT`, optionally preceded by one more base.
+fn has_five_prime_motif(repeat: &str) -> bool {
+    let repeat = repeat.to_ascii_uppercase();
+    repeat.starts_with(FIVE_PRIME_MOTIF)
+        || repeat
+            .get(1..)
+            .is_some_and(|r| r.starts_with(FIVE_PRIME_MOTIF))
+}
+
+/// The fraction of a sequence that is A or T.
+fn at_fraction(sequence: &str) -> f64 {
+    if sequence.is_empty() {
+        return 0.0;
+    }
+    let at = sequence
+        .bytes()
+        .filter(|b| matches!(b.to_ascii_uppercase(), b'A' | b'T' | b'U'))
+        .count();
+    at as f64 / sequence.len() as f64
+}
+
+/// The sign of a number, treating values very close to zero as zero.
+fn sign(value: f64) -> f64 {
+    if value.abs() < 1e-9 {
+        0.0
+    } else {
+        value.signum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, parse_array};
+
+    #[test]
+    fn test_predict_orientation() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let contigs = parse(&input).unwrap();
+        let array = &contigs[1].arrays[0];
+        // Only the degenerate final repeat gives any evidence.
+        let orientation = predict_orientation(array);
+        assert_eq!(orientation.strand, Strand::Forward);
+        assert_eq!(orientation.confidence, 1.0 / 2.5);
+    }
+
+    #[test]
+    fn test_predict_orientation_reverse() {
+        let input = "\nCRISPR 1   Range: 1 - 83
+POSITION	REPEAT	SPACER
+--------	-----	------
+1		GATTCAATCCGTTCCTATAGG	CCCCCCCCCC	[ 21, 10 ]
+32		GTTTCAATCCGTTCCTATAGG	GGGGGGGGGG	[ 21, 10 ]
+63		GTTTCAATCCGTTCCTATAGG
+--------	-----	------
+Repeats: 3	Average Length: 21		Average Length: 10
+";
+        let (_, mut array) = parse_array(input).unwrap();
+        let orientation = predict_orientation(&array);
+        assert_eq!(orientation.strand, Strand::Reverse);
+        array.strand = Some(orientation.strand);
+        assert_eq!(array.strand.unwrap().to_string(), "-");
+    }
+
+    #[test]
+    fn test_motifs() {
+        assert!(has_three_prime_motif("GATAATCTCTTATAGAATTGAAAG"));
+        assert!(has_three_prime_motif("GATAATCTCTTATAGAATTGAAA"));
+        assert!(!has_three_prime_motif("GATAATCTCTTATAGAATTGAAAGG"));
+        assert!(has_five_prime_motif("GTTTCAATCCCTTATAGG"));
+        assert!(has_five_prime_motif("TTTCAATCCCTTATAGG"));
+        assert!(!has_five_prime_motif("GGTTTCAATCCCTTATAGG"));
+    }
+
+    #[test]
+    fn test_no_evidence() {
+        let array = Array {
+            order: 0,
+            start: 0,
+            end: 0,
+            repeat_spacers: vec![],
+            strand: None,
+        };
+        let orientation = predict_orientation(&array);
+        assert_eq!(orientation.strand, Strand::Forward);
+        assert_eq!(orientation.confidence, 0.0);
+    }
+}
@@ -0,0 +1,278 @@
+//! CRISPR arrays that own their sequences.
+
+use crate::sequence::reverse_complement;
+use crate::{Array, Repeat, RepeatOnly, RepeatSpacer, Strand};
+
+#[derive(Clone, Debug, PartialEq)]
+/// An [`Array`] that owns its sequences, for arrays that do not appear verbatim in the MinCED
+/// output, such as an array that has been reverse complemented. It has the same fields as
+/// [`Array`], and [`OwnedArray::as_array`] borrows it as one.
+pub struct OwnedArray {
+    /// The nth CRISPR array in this genome/contig.
+    pub order: usize,
+    /// Zero-indexed inclusive start coordinate.
+    pub start: usize,
+    /// Zero-indexed exclusive end coordinate.
+    pub end: usize,
+    /// The repeats and spacers in this array.
+    pub repeat_spacers: Vec<OwnedRepeat>,
+    /// The strand the sequences are on, if known.
+    pub strand: Option<Strand>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A [`RepeatSpacer`] that owns its sequences.
+pub struct OwnedRepeatSpacer {
+    /// Sequence of the repeat.
+    pub repeat: String,
+    /// Sequence of the spacer.
+    pub spacer: String,
+    /// Zero-indexed inclusive start coordinate, which is the start of the repeat.
+    pub start: usize,
+    /// Zero-indexed exclusive end coordinate, which is the end of the repeat or the spacer,
+    /// whichever is later.
+    pub end: usize,
+    /// Zero-indexed inclusive start coordinate of the spacer.
+    pub spacer_start: usize,
+    /// Zero-indexed exclusive end coordinate of the spacer.
+    pub spacer_end: usize,
+    /// Zero-indexed inclusive start coordinate of the repeat.
+    pub repeat_start: usize,
+    /// Zero-indexed exclusive end coordinate of the repeat.
+    pub repeat_end: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A [`RepeatOnly`] that owns its sequence.
+pub struct OwnedRepeatOnly {
+    /// Sequence of the repeat.
+    pub repeat: String,
+    /// Zero-indexed inclusive start coordinate.
+    pub start: usize,
+    /// Zero-indexed exclusive end coordinate.
+    pub end: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A [`Repeat`] that owns its sequences.
+pub enum OwnedRepeat {
+    /// A repeat with a spacer
+    WithSpacer(OwnedRepeatSpacer),
+    /// A repeat without a spacer (the last repeat in the array)
+    WithoutSpacer(OwnedRepeatOnly),
+}
+
+impl OwnedRepeat {
+    /// Builds a repeat starting at `repeat_start`, followed by a spacer starting at the given
+    /// position if there is one.
+    pub(crate) fn new(
+        repeat: String,
+        repeat_start: usize,
+        spacer: Option<(String, usize)>,
+    ) -> OwnedRepeat {
+        let repeat_end = repeat_start + repeat.len();
+        match spacer {
+            Some((spacer, spacer_start)) => {
+                let spacer_end = spacer_start + spacer.len();
+                OwnedRepeat::WithSpacer(OwnedRepeatSpacer {
+                    repeat,
+                    spacer,
+                    start: repeat_start,
+                    end: repeat_end.max(spacer_end),
+                    spacer_start,
+                    spacer_end,
+                    repeat_start,
+                    repeat_end,
+                })
+            }
+            None => OwnedRepeat::WithoutSpacer(OwnedRepeatOnly {
+                repeat,
+                start: repeat_start,
+                end: repeat_end,
+            }),
+        }
+    }
+
+    /// Borrows this repeat as a [`Repeat`].
+    pub fn as_repeat(&self) -> Repeat<'_> {
+        match self {
+            OwnedRepeat::WithSpacer(rs) => Repeat::WithSpacer(RepeatSpacer {
+                repeat: &rs.repeat,
+                spacer: &rs.spacer,
+                start: rs.start,
+                end: rs.end,
+                spacer_start: rs.spacer_start,
+                spacer_end: rs.spacer_end,
+                repeat_start: rs.repeat_start,
+                repeat_end: rs.repeat_end,
+            }),
+            OwnedRepeat::WithoutSpacer(r) => Repeat::WithoutSpacer(RepeatOnly {
+                repeat: &r.repeat,
+                start: r.start,
+                end: r.end,
+            }),
+        }
+    }
+}
+
+impl From<&Repeat<'_>> for OwnedRepeat {
+    fn from(repeat: &Repeat<'_>) -> OwnedRepeat {
+        match repeat {
+            Repeat::WithSpacer(rs) => OwnedRepeat::WithSpacer(OwnedRepeatSpacer {
+                repeat: rs.repeat.to_string(),
+                spacer: rs.spacer.to_string(),
+                start: rs.start,
+                end: rs.end,
+                spacer_start: rs.spacer_start,
+                spacer_end: rs.spacer_end,
+                repeat_start: rs.repeat_start,
+                repeat_end: rs.repeat_end,
+            }),
+            Repeat::WithoutSpacer(r) => OwnedRepeat::WithoutSpacer(OwnedRepeatOnly {
+                repeat: r.repeat.to_string(),
+                start: r.start,
+                end: r.end,
+            }),
+        }
+    }
+}
+
+impl OwnedArray {
+    /// Borrows this array as an [`Array`].
+    pub fn as_array(&self) -> Array<'_> {
+        Array {
+            order: self.order,
+            start: self.start,
+            end: self.end,
+            repeat_spacers: self
+                .repeat_spacers
+                .iter()
+                .map(OwnedRepeat::as_repeat)
+                .collect(),
+            strand: self.strand,
+        }
+    }
+}
+
+impl From<&Array<'_>> for OwnedArray {
+    fn from(array: &Array<'_>) -> OwnedArray {
+        OwnedArray {
+            order: array.order,
+            start: array.start,
+            end: array.end,
+            repeat_spacers: array.repeat_spacers.iter().map(OwnedRepeat::from).collect(),
+            strand: array.strand,
+        }
+    }
+}
+
+impl<'a> Array<'a> {
+    /// Re-expresses this array on the opposite strand. Every repeat and spacer is reverse
+    /// complemented (including IUPAC ambiguity codes) and the order of the repeats is reversed,
+    /// so that the array reads in the direction of the other strand and the repeat without a
+    /// spacer is once again last. [`Array::strand`] is flipped to match: an array on the
+    /// forward strand, or on an unknown one, ends up on [`Strand::Reverse`], and vice versa.
+    ///
+    /// Coordinates stay genomic, so each repeat keeps its own start and end and is paired with
+    /// the spacer that precedes it on the forward strand. As in the parsed array, each unit
+    /// starts where its repeat starts; since its spacer now lies before the repeat, the unit
+    /// ends where the repeat ends.
+    pub fn reverse_complement(&self) -> OwnedArray {
+        let mut repeats: Vec<(String, usize)> = vec![];
+        let mut spacers: Vec<(String, usize)> = vec![];
+        for repeat in &self.repeat_spacers {
+            match repeat {
+                Repeat::WithSpacer(rs) => {
+                    repeats.push((reverse_complement(rs.repeat), rs.repeat_start));
+                    spacers.push((reverse_complement(rs.spacer), rs.spacer_start));
+                }
+                Repeat::WithoutSpacer(r) => repeats.push((reverse_complement(r.repeat), r.start)),
+            }
+        }
+        let mut spacers = spacers.into_iter().rev();
+        let repeat_spacers = repeats
+            .into_iter()
+            .rev()
+            .map(|(repeat, repeat_start)| OwnedRepeat::new(repeat, repeat_start, spacers.next()))
+            .collect();
+        OwnedArray {
+            order: self.order,
+            start: self.start,
+            end: self.end,
+            repeat_spacers,
+            strand: Some(match self.strand {
+                Some(Strand::Reverse) => Strand::Forward,
+                Some(Strand::Forward) | None => Strand::Reverse,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_reverse_complement() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let contigs = parse(&input).unwrap();
+        let array = &contigs[0].arrays[0];
+        let reversed = array.reverse_complement();
+        let actual = reversed.as_array();
+        let expected = Array {
+            order: 0,
+            start: 10647,
+            end: 10814,
+            repeat_spacers: vec![
+                Repeat::WithSpacer(RepeatSpacer {
+                    start: 10785,
+                    end: 10814,
+                    repeat_start: 10785,
+                    repeat_end: 10814,
+                    spacer_start: 10751,
+                    spacer_end: 10785,
+                    repeat: "TGAGGTGGTGAGATTGGTTGGTGCACTTG",
+                    spacer: "AGACAACTGCACCCCCTGAGAGGTGGTGAGATGG",
+                }),
+                Repeat::WithSpacer(RepeatSpacer {
+                    start: 10722,
+                    end: 10751,
+                    repeat_start: 10722,
+                    repeat_end: 10751,
+                    spacer_start: 10676,
+                    spacer_end: 10722,
+                    repeat: "TGAGGTGGTGAGATTGGTTGGTGCACTTG",
+                    spacer: "TTCTTGGTGCACTTGAGACAAGTGCACCCCCTTTAAGTGCACCCCC",
+                }),
+                Repeat::WithoutSpacer(RepeatOnly {
+                    start: 10647,
+                    end: 10676,
+                    repeat: "TGAGGTGGTGAGATTGGTTGGTGCACTTG",
+                }),
+            ],
+            strand: Some(Strand::Reverse),
+        };
+        assert_eq!(expected, actual);
+        for repeat in &actual.repeat_spacers {
+            if let Repeat::WithSpacer(rs) = repeat {
+                assert_eq!(rs.start, rs.repeat_start);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reverse_complement_twice() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        for contig in parse(&input).unwrap() {
+            for mut array in contig.arrays {
+                array.strand = Some(Strand::Reverse);
+                let once = array.reverse_complement();
+                assert_eq!(once.strand, Some(Strand::Forward));
+                let twice = once.as_array().reverse_complement();
+                assert_eq!(array, twice.as_array());
+                assert_eq!(OwnedArray::from(&array), twice);
+            }
+        }
+    }
+}
@@ -0,0 +1,328 @@
+//! Infers protospacer adjacent motifs (PAMs) from the sequences flanking protospacers.
+
+use crate::fasta::FastaRecord;
+use crate::protospacer::ProtospacerHit;
+use crate::sequence::{base_mask, iupac_code, reverse_complement_bytes};
+use crate::{ArrayId, RepeatFamilies, Strand};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Debug, PartialEq)]
+/// The sequences on either side of a protospacer, oriented like the spacer that matches it.
+/// A side is `None` if the protospacer is too close to the end of the target to have a full
+/// flank on that side.
+pub struct Flanks {
+    /// The bases immediately 5' of the protospacer, ending next to it.
+    pub upstream: Option<Vec<u8>>,
+    /// The bases immediately 3' of the protospacer, starting next to it.
+    pub downstream: Option<Vec<u8>>,
+}
+
+/// Extracts `length` bp on each side of every distinct protospacer. Hits on targets that are not
+/// in `targets` are skipped, and so are repeated hits at the same place, such as those from
+/// several copies of the same spacer.
+///
+/// Targets are looked up by [`FastaRecord::id`]. If several targets share an ID, only the first
+/// one is used, since a hit only records the ID of its target. Hits that run past the end of
+/// their target, which can only come from a different set of targets, are skipped.
+pub fn extract_flanks(
+    hits: &[ProtospacerHit],
+    targets: &[FastaRecord],
+    length: usize,
+) -> Vec<Flanks> {
+    let mut by_id: HashMap<&str, &FastaRecord> = HashMap::new();
+    for target in targets {
+        by_id.entry(target.id()).or_insert(target);
+    }
+    let mut seen = HashSet::new();
+    let mut flanks = vec![];
+    for hit in hits {
+        if !seen.insert((hit.target.as_str(), hit.start, hit.end, hit.strand)) {
+            continue;
+        }
+        let sequence = match by_id.get(hit.target.as_str()) {
+            Some(target) if hit.end <= target.sequence.len() => &target.sequence,
+            _ => continue,
+        };
+        let before = hit
+            .start
+            .checked_sub(length)
+            .and_then(|start| sequence.get(start..hit.start))
+            .map(|s| s.to_ascii_uppercase());
+        let after = hit
+            .end
+            .checked_add(length)
+            .and_then(|end| sequence.get(hit.end..end))
+            .map(|s| s.to_ascii_uppercase());
+        let flank = match hit.strand {
+            Strand::Forward => Flanks {
+                upstream: before,
+                downstream: after,
+            },
+            Strand::Reverse => Flanks {
+                upstream: after.map(|s| reverse_complement_bytes(&s)),
+                downstream: before.map(|s| reverse_complement_bytes(&s)),
+            },
+        };
+        flanks.push(flank);
+    }
+    flanks
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// The number of times each of A, C, G and T is seen at each position of a set of aligned
+/// sequences. Ambiguous bases are not counted.
+pub struct PositionFrequencyMatrix {
+    /// The counts of A, C, G and T at each position.
+    pub counts: Vec<[usize; 4]>,
+}
+
+impl PositionFrequencyMatrix {
+    /// Counts the bases at each position of `sequences`, which should all be `length` long.
+    pub fn new<'s>(
+        sequences: impl IntoIterator<Item = &'s [u8]>,
+        length: usize,
+    ) -> PositionFrequencyMatrix {
+        let mut counts = vec![[0; 4]; length];
+        for sequence in sequences {
+            for (column, &base) in counts.iter_mut().zip(sequence) {
+                let mask = base_mask(base);
+                if mask.is_power_of_two() {
+                    column[mask.trailing_zeros() as usize] += 1;
+                }
+            }
+        }
+        PositionFrequencyMatrix { counts }
+    }
+
+    /// The information content of each position in bits, from 0 (no preference for any base) to
+    /// 2 (always the same base).
+    pub fn information_content(&self) -> Vec<f64> {
+        self.counts
+            .iter()
+            .map(|column| {
+                let total: usize = column.iter().sum();
+                if total == 0 {
+                    return 0.0;
+                }
+                let entropy: f64 = column
+                    .iter()
+                    .filter(|&&count| count > 0)
+                    .map(|&count| {
+                        let p = count as f64 / total as f64;
+                        -p * p.log2()
+                    })
+                    .sum();
+                2.0 - entropy
+            })
+            .collect()
+    }
+
+    /// The total information content of all positions, in bits.
+    pub fn total_information(&self) -> f64 {
+        self.information_content().iter().sum()
+    }
+
+    /// The consensus sequence, using IUPAC codes. A position gets a single base if that base
+    /// makes up more than half of the counts and is at least twice as common as the next one,
+    /// the code for the two most common bases if together they make up more than three quarters,
+    /// and `N` otherwise.
+    pub fn consensus(&self) -> String {
+        let consensus: Vec<u8> = self
+            .counts
+            .iter()
+            .map(|column| {
+                let total: usize = column.iter().sum();
+                let mut order = [0, 1, 2, 3];
+                order.sort_by_key(|&i| std::cmp::Reverse(column[i]));
+                let (first, second) = (column[order[0]], column[order[1]]);
+                if total > 0 && 2 * first > total && first >= 2 * second {
+                    iupac_code(1 << order[0])
+                } else if total > 0 && 4 * (first + second) > 3 * total {
+                    iupac_code(1 << order[0] | 1 << order[1])
+                } else {
+                    b'N'
+                }
+            })
+            .collect();
+        String::from_utf8(consensus).expect("IUPAC codes are ASCII")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Base frequencies on either side of a set of protospacers, from which a PAM can be read.
+pub struct PamModel {
+    /// The number of protospacers with a full upstream and downstream flank, respectively.
+    pub flank_counts: (usize, usize),
+    /// Base frequencies upstream (5') of the protospacers, ending next to them.
+    pub upstream: PositionFrequencyMatrix,
+    /// Base frequencies downstream (3') of the protospacers, starting next to them.
+    pub downstream: PositionFrequencyMatrix,
+}
+
+impl PamModel {
+    /// Builds a model from the flanks of a set of protospacers, which should all be `length`
+    /// long.
+    pub fn from_flanks(flanks: &[Flanks], length: usize) -> PamModel {
+        let upstream: Vec<&[u8]> = flanks
+            .iter()
+            .filter_map(|f| f.upstream.as_deref())
+            .collect();
+        let downstream: Vec<&[u8]> = flanks
+            .iter()
+            .filter_map(|f| f.downstream.as_deref())
+            .collect();
+        PamModel {
+            flank_counts: (upstream.len(), downstream.len()),
+            upstream: PositionFrequencyMatrix::new(upstream, length),
+            downstream: PositionFrequencyMatrix::new(downstream, length),
+        }
+    }
+
+    /// Builds a model from `length` bp on each side of each protospacer hit.
+    pub fn from_hits(hits: &[ProtospacerHit], targets: &[FastaRecord], length: usize) -> PamModel {
+        PamModel::from_flanks(&extract_flanks(hits, targets, length), length)
+    }
+
+    /// Builds a model from the hits of the spacers of one repeat family, since arrays of the
+    /// same subtype share a PAM.
+    pub fn for_family(
+        hits: &[ProtospacerHit],
+        targets: &[FastaRecord],
+        families: &RepeatFamilies,
+        family: usize,
+        length: usize,
+    ) -> PamModel {
+        let hits: Vec<ProtospacerHit> = hits
+            .iter()
+            .filter(|hit| {
+                let array = ArrayId {
+                    accession: hit.accession,
+                    order: hit.order,
+                };
+                families.family_of(&array) == Some(family)
+            })
+            .cloned()
+            .collect();
+        PamModel::from_hits(&hits, targets, length)
+    }
+
+    /// The side with more information, which is where the PAM most likely is, along with its
+    /// consensus and total information content in bits. Ties go to the upstream side.
+    pub fn pam(&self) -> (PamSide, String, f64) {
+        let upstream = self.upstream.total_information();
+        let downstream = self.downstream.total_information();
+        if downstream > upstream {
+            (PamSide::Downstream, self.downstream.consensus(), downstream)
+        } else {
+            (PamSide::Upstream, self.upstream.consensus(), upstream)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Which side of the protospacer a PAM is on, relative to the spacer's orientation.
+pub enum PamSide {
+    /// 5' of the protospacer, as for type I and V systems.
+    Upstream,
+    /// 3' of the protospacer, as for type II systems.
+    Downstream,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fasta::FastaReader;
+    use crate::protospacer::ProtospacerSearch;
+    use crate::{parse, FamilyOptions};
+
+    const INPUT: &str = "Sequence 'a' (1000 bp)
+
+CRISPR 1   Range: 1 - 108
+POSITION	REPEAT	SPACER
+--------	-----	------
+1		GTTTCAAT	AAAACCCCGGGGTTTTACGT	[ 8, 20 ]
+29		GTTTCAAT	GCGCGCATATGCGCATATGC	[ 8, 20 ]
+57		GTTTCAAT	CATCATCATGATGATGCCAA	[ 8, 20 ]
+85		GTTTCAAT
+--------	-----	------
+Repeats: 4	Average Length: 8		Average Length: 20
+
+Time to find repeats: 1 ms
+
+
+";
+
+    // Each protospacer has a TTC upstream, one of them on the reverse strand.
+    const TARGETS: &str = ">phage_1
+GATTCAAAACCCCGGGGTTTTACGTAGG
+>phage_2
+CCTGCATATGCGCATATGCGCGCGAACA
+>phage_3
+TTTCCATCATCATGATGATGCCAATGA
+";
+
+    fn model(length: usize) -> PamModel {
+        let contigs = parse(INPUT).unwrap();
+        let search = ProtospacerSearch::new(&contigs, 0);
+        let targets: Vec<FastaRecord> = FastaReader::new(TARGETS.as_bytes())
+            .map(|r| r.unwrap())
+            .collect();
+        let hits = search
+            .search_fasta(FastaReader::new(TARGETS.as_bytes()))
+            .unwrap();
+        let families = RepeatFamilies::new(&contigs, &FamilyOptions::default());
+        PamModel::for_family(&hits, &targets, &families, 0, length)
+    }
+
+    #[test]
+    fn test_pam_model() {
+        let model = model(3);
+        assert_eq!(model.flank_counts, (3, 3));
+        assert_eq!(model.upstream.counts[0], [0, 0, 0, 3]);
+        let (side, consensus, information) = model.pam();
+        assert_eq!(side, PamSide::Upstream);
+        assert_eq!(consensus, "TTC");
+        assert_eq!(information, 6.0);
+    }
+
+    #[test]
+    fn test_flanks_near_ends() {
+        // phage_3 only has four bases upstream of its protospacer, and none of the protospacers
+        // have more than three bases downstream.
+        let model = model(5);
+        assert_eq!(model.flank_counts, (2, 0));
+        assert_eq!(model.upstream.consensus(), "KRTTC");
+    }
+
+    #[test]
+    fn test_mismatched_targets() {
+        let contigs = parse(INPUT).unwrap();
+        let hits = ProtospacerSearch::new(&contigs, 0)
+            .search_fasta(FastaReader::new(TARGETS.as_bytes()))
+            .unwrap();
+        // A shorter phage_1 comes first, so the hit on the real phage_1 runs past its end and
+        // is skipped rather than read out of bounds.
+        let targets: Vec<FastaRecord> =
+            FastaReader::new(format!(">phage_1\nGATTC\n{}", TARGETS).as_bytes())
+                .map(|r| r.unwrap())
+                .collect();
+        let flanks = extract_flanks(&hits, &targets, 3);
+        assert_eq!(flanks.len(), 2);
+        assert!(flanks
+            .iter()
+            .all(|f| f.upstream.as_deref() == Some(&b"TTC"[..])));
+        // A flank too long to add to a coordinate is missing rather than overflowing.
+        let flanks = extract_flanks(&hits, &targets, usize::MAX);
+        assert!(flanks
+            .iter()
+            .all(|f| f.upstream.is_none() && f.downstream.is_none()));
+    }
+
+    #[test]
+    fn test_consensus() {
+        let sequences: [&[u8]; 4] = [b"AAGN", b"ACGT", b"ACTA", b"ATTC"];
+        let pfm = PositionFrequencyMatrix::new(sequences, 4);
+        assert_eq!(pfm.consensus(), "ANKN");
+        assert_eq!(pfm.information_content()[0], 2.0);
+    }
+}
@@ -0,0 +1,75 @@
+//! Parsing a single report on several threads.
+
+use crate::{parse_contig_arrays, record_ranges, Contig};
+use nom::{error::Error, Err};
+use rayon::prelude::*;
+
+/// Parses the output of minCED using all available threads.
+///
+/// The input is split at each `Sequence '` line and the contigs are parsed concurrently, then
+/// returned in the order they appear in the input. As with [`parse`](crate::parse), nothing is
+/// returned if the input does not start with a `Sequence '` line, and parsing stops at the first
+/// contig that cannot be parsed or that is followed by text that is not another contig, so both
+/// functions return the same contigs.
+pub fn parse_parallel(input: &str) -> Result<Vec<Contig<'_>>, Err<Error<&str>>> {
+    let results: Vec<_> = record_ranges(input.as_bytes())
+        .into_par_iter()
+        .map(|range| parse_contig_arrays(&input[range]))
+        .collect();
+    let mut contigs = Vec::with_capacity(results.len());
+    for result in results {
+        match result {
+            Ok((remainder, contig)) => {
+                contigs.push(contig);
+                if !remainder.is_empty() {
+                    break;
+                }
+            }
+            Err(Err::Error(_)) => break,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(contigs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_parse_parallel() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let expected = parse(&input).unwrap();
+        let actual = parse_parallel(&input).unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_parse_parallel_stops_at_bad_record() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let broken = input.replacen("CRISPR 2   Range", "CRISPR 2   Rnage", 1);
+        let expected = parse(&broken).unwrap();
+        let actual = parse_parallel(&broken).unwrap();
+        assert_eq!(actual.len(), 1);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_parse_parallel_stops_where_parse_does() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let preamble = format!("minced 0.4.2\n{}", input);
+        assert_eq!(parse(&preamble).unwrap(), vec![]);
+        assert_eq!(parse_parallel(&preamble).unwrap(), vec![]);
+
+        // A stray line after the second contig ends the report.
+        let stray = input.replacen(
+            "Sequence 'MGYG000242676_4'",
+            "stray line\nSequence 'MGYG000242676_4'",
+            1,
+        );
+        let expected = parse(&stray).unwrap();
+        assert_eq!(expected.len(), 2);
+        assert_eq!(expected, parse_parallel(&stray).unwrap());
+    }
+}
@@ -0,0 +1,309 @@
+//! Searches target sequences, such as phage and plasmid genomes, for matches to spacers.
+
+use crate::fasta::{FastaReader, FastaRecord};
+use crate::seeds::{is_ambiguous, normalize, pieces};
+use crate::sequence::{bases_match, reverse_complement_bytes};
+use crate::{Contig, Repeat, Strand};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::{self, BufRead};
+
+#[derive(Clone, Debug, PartialEq)]
+/// A match between a spacer and a target sequence.
+pub struct ProtospacerHit<'a> {
+    /// Accession of the contig/genome the spacer is in.
+    pub accession: &'a str,
+    /// The order of the array the spacer is in (see [`Array::order`](crate::Array::order)).
+    pub order: usize,
+    /// The index of the spacer's unit in [`Array::repeat_spacers`](crate::Array::repeat_spacers).
+    pub index: usize,
+    /// Sequence of the spacer.
+    pub spacer: &'a str,
+    /// The ID of the target sequence.
+    pub target: String,
+    /// Zero-indexed inclusive start coordinate of the protospacer on the target's forward strand.
+    pub start: usize,
+    /// Zero-indexed exclusive end coordinate of the protospacer on the target's forward strand.
+    pub end: usize,
+    /// The strand of the target the spacer matches. On the reverse strand, the protospacer is the
+    /// reverse complement of the target between `start` and `end`.
+    pub strand: Strand,
+    /// The zero-indexed positions within the spacer that differ from the protospacer.
+    pub mismatches: Vec<usize>,
+}
+
+/// An index of every spacer in a collection of contigs, which can be searched against target
+/// sequences.
+///
+/// Identical spacers are only indexed once. Each spacer is cut into `max_mismatches + 1` pieces,
+/// at least one of which must match the target exactly, and those pieces are used as seeds that
+/// are extended into full matches. Matching is case-insensitive, and ambiguity codes in either
+/// sequence match any base they stand for. Since those matches are not exact, spacers with
+/// ambiguity codes are compared at every position of the target, and stretches of the target
+/// with ambiguity codes are compared with every seed, so both are slower to search.
+pub struct ProtospacerSearch<'a> {
+    max_mismatches: usize,
+    /// Each distinct spacer sequence, in upper case with U written as T.
+    spacers: Vec<Vec<u8>>,
+    /// The occurrences of each distinct spacer.
+    occurrences: Vec<Vec<(&'a str, usize, usize, &'a str)>>,
+    /// Each seed, with the spacers it comes from and its offset within them.
+    seeds: HashMap<Vec<u8>, Vec<(usize, usize)>>,
+    seed_lengths: BTreeSet<usize>,
+    /// Spacers with ambiguity codes, which have no seeds.
+    unseeded: Vec<usize>,
+}
+
+impl<'a> ProtospacerSearch<'a> {
+    /// Indexes the spacers in `contigs`, to find matches with up to `max_mismatches`
+    /// substitutions. Spacers shorter than `max_mismatches + 1` are never matched.
+    pub fn new(contigs: &[Contig<'a>], max_mismatches: usize) -> ProtospacerSearch<'a> {
+        let mut search = ProtospacerSearch {
+            max_mismatches,
+            spacers: vec![],
+            occurrences: vec![],
+            seeds: HashMap::new(),
+            seed_lengths: BTreeSet::new(),
+            unseeded: vec![],
+        };
+        let mut ids: HashMap<Vec<u8>, usize> = HashMap::new();
+        for contig in contigs {
+            for array in &contig.arrays {
+                for (index, repeat) in array.repeat_spacers.iter().enumerate() {
+                    let spacer = match repeat {
+                        Repeat::WithSpacer(rs) => rs.spacer,
+                        Repeat::WithoutSpacer(_) => continue,
+                    };
+                    let key = normalize(spacer.as_bytes());
+                    let id = match ids.get(&key) {
+                        Some(&id) => id,
+                        None => {
+                            let id = search.spacers.len();
+                            search.add_seeds(id, &key);
+                            ids.insert(key.clone(), id);
+                            search.spacers.push(key);
+                            search.occurrences.push(vec![]);
+                            id
+                        }
+                    };
+                    search.occurrences[id].push((contig.accession, array.order, index, spacer));
+                }
+            }
+        }
+        search
+    }
+
+    /// Cuts a spacer into pieces and indexes them.
+    fn add_seeds(&mut self, id: usize, spacer: &[u8]) {
+        let pieces = pieces(spacer.len(), self.max_mismatches);
+        if !pieces.is_empty() && is_ambiguous(spacer) {
+            self.unseeded.push(id);
+            return;
+        }
+        for piece in pieces {
+            self.seed_lengths.insert(piece.len());
+            let offset = piece.start;
+            self.seeds
+                .entry(spacer[piece].to_vec())
+                .or_default()
+                .push((id, offset));
+        }
+    }
+
+    /// Finds every match on both strands of a single target sequence, ordered by position, then
+    /// strand, then spacer.
+    pub fn search(&self, target: &FastaRecord) -> Vec<ProtospacerHit<'a>> {
+        let forward = normalize(&target.sequence);
+        let reverse = reverse_complement_bytes(&forward);
+        let mut found: Vec<(usize, Strand, usize, Vec<usize>)> = vec![];
+        for (sequence, strand) in [(&forward, Strand::Forward), (&reverse, Strand::Reverse)] {
+            for (start, id, mismatches) in self.scan(sequence) {
+                let length = self.spacers[id].len();
+                // Report coordinates on the forward strand.
+                let start = match strand {
+                    Strand::Forward => start,
+                    Strand::Reverse => sequence.len() - start - length,
+                };
+                found.push((start, strand, id, mismatches));
+            }
+        }
+        found.sort_by_key(|(start, strand, id, _)| (*start, *strand == Strand::Reverse, *id));
+
+        let mut hits = vec![];
+        for (start, strand, id, mismatches) in found {
+            for &(accession, order, index, spacer) in &self.occurrences[id] {
+                hits.push(ProtospacerHit {
+                    accession,
+                    order,
+                    index,
+                    spacer,
+                    target: target.id().to_string(),
+                    start,
+                    end: start + self.spacers[id].len(),
+                    strand,
+                    mismatches: mismatches.clone(),
+                });
+            }
+        }
+        hits
+    }
+
+    /// Finds every match in every record of a FASTA file, in the order of the records.
+    pub fn search_fasta<R: BufRead>(
+        &self,
+        reader: FastaReader<R>,
+    ) -> io::Result<Vec<ProtospacerHit<'a>>> {
+        let mut hits = vec![];
+        for record in reader {
+            hits.extend(self.search(&record?));
+        }
+        Ok(hits)
+    }
+
+    /// Finds the start of every match on one strand, with the spacer it matches and the positions
+    /// of the mismatches.
+    fn scan(&self, sequence: &[u8]) -> Vec<(usize, usize, Vec<usize>)> {
+        let mut checked: HashSet<(usize, usize)> = HashSet::new();
+        let mut matches = vec![];
+        let mut check = |start: usize, id: usize| {
+            let spacer = &self.spacers[id];
+            if start + spacer.len() > sequence.len() || !checked.insert((start, id)) {
+                return;
+            }
+            let mismatches: Vec<usize> = spacer
+                .iter()
+                .zip(&sequence[start..])
+                .enumerate()
+                .filter(|&(_, (&a, &b))| !bases_match(a, b))
+                .map(|(i, _)| i)
+                .collect();
+            if mismatches.len() <= self.max_mismatches {
+                matches.push((start, id, mismatches));
+            }
+        };
+        for &length in &self.seed_lengths {
+            for (position, window) in sequence.windows(length).enumerate() {
+                // A window with ambiguity codes can match seeds that differ from it.
+                let seeds: Vec<&(usize, usize)> = if is_ambiguous(window) {
+                    self.seeds
+                        .iter()
+                        .filter(|(seed, _)| {
+                            seed.len() == length
+                                && seed.iter().zip(window).all(|(&a, &b)| bases_match(a, b))
+                        })
+                        .flat_map(|(_, seeds)| seeds)
+                        .collect()
+                } else {
+                    self.seeds.get(window).into_iter().flatten().collect()
+                };
+                for &(id, offset) in seeds {
+                    if let Some(start) = position.checked_sub(offset) {
+                        check(start, id);
+                    }
+                }
+            }
+        }
+        for &id in &self.unseeded {
+            for start in 0..sequence.len() {
+                check(start, id);
+            }
+        }
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    const INPUT: &str = "Sequence 'a' (1000 bp)
+
+CRISPR 1   Range: 1 - 80
+POSITION	REPEAT	SPACER
+--------	-----	------
+1		GTTTCAAT	AAAACCCCGGGGTTTTACGT	[ 8, 20 ]
+29		GTTTCAAT	GCGCGCATATGCGCATATGC	[ 8, 20 ]
+57		GTTTCAAT
+--------	-----	------
+Repeats: 3	Average Length: 8		Average Length: 20
+
+Time to find repeats: 1 ms
+
+
+Sequence 'b' (1000 bp)
+
+CRISPR 2   Range: 1 - 52
+POSITION	REPEAT	SPACER
+--------	-----	------
+1		GTTTCAAT	AAAACCCCGGGGTTTTACGT	[ 8, 20 ]
+29		GTTTCAAT
+--------	-----	------
+Repeats: 2	Average Length: 8		Average Length: 20
+
+Time to find repeats: 1 ms
+
+
+";
+
+    const TARGETS: &str = ">phage_1 test phage
+TTTTTAAAACCCCGGGGTTTTACGTTT
+TTTTT
+>phage_2
+ccacatatgcgcatatgcgcgcaa
+>phage_3
+AAAACCCCGNNNTTTTACGT
+";
+
+    #[test]
+    fn test_search() {
+        let contigs = parse(INPUT).unwrap();
+        let search = ProtospacerSearch::new(&contigs, 2);
+        let hits = search
+            .search_fasta(FastaReader::new(TARGETS.as_bytes()))
+            .unwrap();
+        let summary: Vec<String> = hits
+            .iter()
+            .map(|h| {
+                format!(
+                    "{} {} {} {}-{} {} {:?}",
+                    h.accession, h.index, h.target, h.start, h.end, h.strand, h.mismatches
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                "a 0 phage_1 5-25 + []",
+                "b 0 phage_1 5-25 + []",
+                "a 1 phage_2 2-22 - [19]",
+                "a 0 phage_3 0-20 + []",
+                "b 0 phage_3 0-20 + []",
+            ]
+        );
+
+        // Ns match any base, so the Ns in phage_3 are not mismatches, but the seeds around them
+        // have to be found without an exact match.
+        let search = ProtospacerSearch::new(&contigs, 0);
+        let target = FastaRecord {
+            header: "phage_3".to_string(),
+            sequence: b"AAAACCCCGNNNTTTTACGT".to_vec(),
+        };
+        let hits = search.search(&target);
+        assert_eq!(hits.len(), 2);
+        assert_eq!((hits[0].start, hits[0].end), (0, 20));
+        assert!(hits[0].mismatches.is_empty());
+    }
+
+    #[test]
+    fn test_search_ambiguous_spacer() {
+        let input = INPUT.replace("GCGCGCATATGCGCATATGC", "GCGCGCATATGCGCATRTGC");
+        let contigs = parse(&input).unwrap();
+        let search = ProtospacerSearch::new(&contigs, 1);
+        let hits = search
+            .search_fasta(FastaReader::new(TARGETS.as_bytes()))
+            .unwrap();
+        let hit = hits.iter().find(|h| h.target == "phage_2").unwrap();
+        assert_eq!((hit.start, hit.end, hit.strand), (2, 22, Strand::Reverse));
+        assert_eq!(hit.mismatches, vec![19]);
+    }
+}
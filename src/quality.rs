@@ -0,0 +1,322 @@
+//! Scores how likely an array is to be a real CRISPR array rather than some other kind of
+//! repeat, so that spurious calls can be filtered out.
+
+use crate::sequence::{entropy, identity};
+use crate::Array;
+
+#[derive(Clone, Debug, PartialEq)]
+/// Thresholds for [`Array::quality`]. Each threshold that an array fails adds a
+/// [`QualityFlag`] and takes the flag's penalty off the array's score.
+pub struct QualityOptions {
+    /// The fewest repeats a convincing array has.
+    pub min_repeats: usize,
+    /// The largest coefficient of variation (standard deviation over mean) of spacer length.
+    pub max_spacer_length_variation: f64,
+    /// The largest identity between a spacer and the consensus repeat.
+    pub max_spacer_repeat_identity: f64,
+    /// The largest mean identity between pairs of spacers.
+    pub max_spacer_identity: f64,
+    /// The lowest mean identity between the repeats and the consensus repeat.
+    pub min_repeat_conservation: f64,
+    /// The lowest entropy of the consensus repeat, in bits per base.
+    pub min_repeat_entropy: f64,
+    /// The lowest score at which an array passes.
+    pub min_score: f64,
+}
+
+impl Default for QualityOptions {
+    fn default() -> QualityOptions {
+        QualityOptions {
+            min_repeats: 3,
+            max_spacer_length_variation: 0.2,
+            max_spacer_repeat_identity: 0.6,
+            max_spacer_identity: 0.6,
+            min_repeat_conservation: 0.85,
+            min_repeat_entropy: 1.5,
+            min_score: 0.5,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// The measurements an array's quality is scored on.
+pub struct QualityFeatures {
+    /// The number of repeats.
+    pub repeat_count: usize,
+    /// The coefficient of variation (standard deviation over mean) of spacer length.
+    pub spacer_length_variation: f64,
+    /// The highest identity between a spacer and the consensus repeat.
+    pub spacer_repeat_identity: f64,
+    /// The mean identity between every pair of spacers.
+    pub spacer_identity: f64,
+    /// The mean identity between each repeat and the consensus repeat.
+    pub repeat_conservation: f64,
+    /// The entropy of the consensus repeat, in bits per base.
+    pub repeat_entropy: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A reason an array looks less like a CRISPR array.
+pub enum QualityFlag {
+    /// The array has too few repeats.
+    FewRepeats {
+        /// The number of repeats.
+        count: usize,
+    },
+    /// The spacers vary too much in length.
+    VariableSpacerLength {
+        /// The coefficient of variation of spacer length.
+        variation: f64,
+    },
+    /// A spacer is too similar to the repeat, as in short tandem repeats.
+    SpacerResemblesRepeat {
+        /// The highest identity between a spacer and the consensus repeat.
+        identity: f64,
+    },
+    /// The spacers are too similar to each other, as in tandem repeats.
+    SimilarSpacers {
+        /// The mean identity between pairs of spacers.
+        identity: f64,
+    },
+    /// The repeats are too different from each other.
+    PoorlyConservedRepeats {
+        /// The mean identity between each repeat and the consensus repeat.
+        conservation: f64,
+    },
+    /// The repeat is made of only one or two bases.
+    LowComplexityRepeat {
+        /// The entropy of the consensus repeat, in bits per base.
+        entropy: f64,
+    },
+}
+
+impl QualityFlag {
+    /// How much the flag takes off an array's score.
+    pub fn penalty(&self) -> f64 {
+        match self {
+            QualityFlag::FewRepeats { .. } => 0.3,
+            QualityFlag::VariableSpacerLength { .. } => 0.2,
+            QualityFlag::SpacerResemblesRepeat { .. } => 0.3,
+            QualityFlag::SimilarSpacers { .. } => 0.4,
+            QualityFlag::PoorlyConservedRepeats { .. } => 0.2,
+            QualityFlag::LowComplexityRepeat { .. } => 0.4,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+/// Evidence levels in the style of CRISPRCasFinder, from 1 (weakest) to 4 (strongest).
+pub enum EvidenceLevel {
+    /// Fewer than four spacers, too few to judge the array on.
+    Level1,
+    /// Spacers or repeats that look like some other kind of repeat: similar spacers, spacers
+    /// that resemble the repeat, or a low-complexity repeat.
+    Level2,
+    /// CRISPR-like, but with poorly conserved repeats or spacers of uneven length.
+    Level3,
+    /// No reason to doubt the array.
+    Level4,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// How convincing an array is.
+pub struct ArrayQuality {
+    /// The measurements the score is based on.
+    pub features: QualityFeatures,
+    /// Every threshold the array failed, in the order they are listed in [`QualityOptions`].
+    pub flags: Vec<QualityFlag>,
+    /// One minus the penalties of the flags, from 0 to 1.
+    pub score: f64,
+    /// Whether the score is at least [`QualityOptions::min_score`].
+    pub passes: bool,
+    /// The CRISPRCasFinder-style evidence level.
+    pub evidence_level: EvidenceLevel,
+}
+
+impl<'a> Array<'a> {
+    /// Scores how likely this array is to be a real CRISPR array, flagging anything that
+    /// suggests it might be some other kind of repeat.
+    pub fn quality(&self, options: &QualityOptions) -> ArrayQuality {
+        let features = self.quality_features();
+        let mut flags = vec![];
+        if features.repeat_count < options.min_repeats {
+            flags.push(QualityFlag::FewRepeats {
+                count: features.repeat_count,
+            });
+        }
+        if features.spacer_length_variation > options.max_spacer_length_variation {
+            flags.push(QualityFlag::VariableSpacerLength {
+                variation: features.spacer_length_variation,
+            });
+        }
+        if features.spacer_repeat_identity > options.max_spacer_repeat_identity {
+            flags.push(QualityFlag::SpacerResemblesRepeat {
+                identity: features.spacer_repeat_identity,
+            });
+        }
+        if features.spacer_identity > options.max_spacer_identity {
+            flags.push(QualityFlag::SimilarSpacers {
+                identity: features.spacer_identity,
+            });
+        }
+        if features.repeat_conservation < options.min_repeat_conservation {
+            flags.push(QualityFlag::PoorlyConservedRepeats {
+                conservation: features.repeat_conservation,
+            });
+        }
+        if features.repeat_entropy < options.min_repeat_entropy {
+            flags.push(QualityFlag::LowComplexityRepeat {
+                entropy: features.repeat_entropy,
+            });
+        }
+
+        let score = (1.0 - flags.iter().map(QualityFlag::penalty).sum::<f64>()).max(0.0);
+        let evidence_level = if features.repeat_count < 5 {
+            EvidenceLevel::Level1
+        } else if flags.iter().any(|flag| {
+            matches!(
+                flag,
+                QualityFlag::SpacerResemblesRepeat { .. }
+                    | QualityFlag::SimilarSpacers { .. }
+                    | QualityFlag::LowComplexityRepeat { .. }
+            )
+        }) {
+            EvidenceLevel::Level2
+        } else if flags.is_empty() {
+            EvidenceLevel::Level4
+        } else {
+            EvidenceLevel::Level3
+        };
+        ArrayQuality {
+            features,
+            passes: score >= options.min_score,
+            flags,
+            score,
+            evidence_level,
+        }
+    }
+
+    fn quality_features(&self) -> QualityFeatures {
+        let consensus = self.consensus_repeat();
+        let spacers: Vec<&str> = self
+            .repeat_spacers
+            .iter()
+            .filter_map(|r| r.spacer())
+            .collect();
+
+        let lengths: Vec<f64> = spacers.iter().map(|s| s.len() as f64).collect();
+        let mean = mean_of(lengths.iter().copied());
+        let variance = mean_of(lengths.iter().map(|l| (l - mean).powi(2)));
+        let spacer_length_variation = if mean > 0.0 {
+            variance.sqrt() / mean
+        } else {
+            0.0
+        };
+
+        let spacer_repeat_identity = spacers
+            .iter()
+            .map(|s| identity(s, &consensus))
+            .fold(0.0, f64::max);
+        let spacer_identity = mean_of(
+            spacers
+                .iter()
+                .enumerate()
+                .flat_map(|(i, a)| spacers[i + 1..].iter().map(move |b| identity(a, b))),
+        );
+        let repeat_conservation = mean_of(
+            self.repeat_spacers
+                .iter()
+                .map(|r| identity(r.repeat(), &consensus)),
+        );
+
+        QualityFeatures {
+            repeat_count: self.repeat_spacers.len(),
+            spacer_length_variation,
+            spacer_repeat_identity,
+            spacer_identity,
+            repeat_conservation,
+            repeat_entropy: entropy(&consensus),
+        }
+    }
+}
+
+/// The mean of some values, or zero if there are none.
+fn mean_of(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    if count == 0 {
+        0.0
+    } else {
+        sum / count as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    const TANDEM: &str = "Sequence 'chr1' (200 bp)
+
+CRISPR 1   Range: 1 - 60
+POSITION\tREPEAT\tSPACER
+--------\t-----\t------
+1\t\tATATATATAT\tATATATAT\t[ 10, 8 ]
+19\t\tATATATATAT\tATATATTT\t[ 10, 8 ]
+37\t\tATATATATAT\tATATATAT\t[ 10, 8 ]
+55\t\tATATATATAT
+--------\t-----\t------
+Repeats: 4\tAverage Length: 10\t\tAverage Length: 8
+
+Time to find repeats: 1 ms
+
+
+";
+
+    #[test]
+    fn test_real_array() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let contigs = parse(&input).unwrap();
+        let options = QualityOptions::default();
+        let quality = contigs[3].arrays[0].quality(&options);
+        assert!(quality.passes);
+        assert_eq!(quality.evidence_level, EvidenceLevel::Level4);
+        assert_eq!(quality.flags, vec![]);
+        assert_eq!(quality.score, 1.0);
+        assert_eq!(quality.features.repeat_conservation, 1.0);
+
+        // Only three repeats.
+        let quality = contigs[0].arrays[0].quality(&options);
+        assert_eq!(quality.evidence_level, EvidenceLevel::Level1);
+        assert!(quality.passes);
+    }
+
+    #[test]
+    fn test_tandem_repeat() {
+        let contigs = parse(TANDEM).unwrap();
+        let quality = contigs[0].arrays[0].quality(&QualityOptions::default());
+        let flags: Vec<&str> = quality
+            .flags
+            .iter()
+            .map(|flag| match flag {
+                QualityFlag::FewRepeats { .. } => "few repeats",
+                QualityFlag::VariableSpacerLength { .. } => "variable spacer length",
+                QualityFlag::SpacerResemblesRepeat { .. } => "spacer resembles repeat",
+                QualityFlag::SimilarSpacers { .. } => "similar spacers",
+                QualityFlag::PoorlyConservedRepeats { .. } => "poorly conserved",
+                QualityFlag::LowComplexityRepeat { .. } => "low complexity",
+            })
+            .collect();
+        assert_eq!(
+            flags,
+            vec![
+                "spacer resembles repeat",
+                "similar spacers",
+                "low complexity"
+            ]
+        );
+        assert_eq!(quality.score, 0.0);
+        assert!(!quality.passes);
+        assert_eq!(quality.features.spacer_identity, 11.0 / 12.0);
+        assert_eq!(quality.features.repeat_entropy, 1.0);
+    }
+}
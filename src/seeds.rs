@@ -0,0 +1,144 @@
+//! Finds sequences that might be within a small distance of a query, using the pigeonhole
+//! principle: if two sequences differ by at most `d` substitutions, insertions or deletions, and
+//! one of them is cut into `d + 1` pieces, at least one piece appears unchanged in the other.
+//!
+//! Pieces are looked up verbatim, but an ambiguity code matches bases other than itself, so
+//! sequences and queries with ambiguity codes skip the lookup and are compared with everything.
+
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+
+/// An index of sequences, each identified by a number, that can be searched for the sequences
+/// that might be within `max_distance` of a query. Candidates still need to be checked.
+pub(crate) struct SeedIndex {
+    max_distance: usize,
+    seeds: HashMap<Vec<u8>, Vec<usize>>,
+    seed_lengths: BTreeSet<usize>,
+    /// Sequences too short to cut into enough pieces, or with ambiguity codes, which are
+    /// candidates for every query.
+    unseeded: Vec<usize>,
+    /// Every sequence in the index, which are the candidates for queries with ambiguity codes.
+    ids: Vec<usize>,
+}
+
+/// Cuts a sequence of the given length into `max_distance + 1` pieces, spreading the remainder
+/// over the first few. A sequence too short to cut into that many pieces has none.
+pub(crate) fn pieces(length: usize, max_distance: usize) -> Vec<Range<usize>> {
+    let pieces = max_distance + 1;
+    if length < pieces {
+        return vec![];
+    }
+    let mut start = 0;
+    (0..pieces)
+        .map(|piece| {
+            let end = start + length / pieces + usize::from(piece < length % pieces);
+            let range = start..end;
+            start = end;
+            range
+        })
+        .collect()
+}
+
+/// Capitalizes a sequence and writes U as T, so that pieces that match are identical.
+pub(crate) fn normalize(sequence: &[u8]) -> Vec<u8> {
+    sequence
+        .iter()
+        .map(|b| match b.to_ascii_uppercase() {
+            b'U' => b'T',
+            b => b,
+        })
+        .collect()
+}
+
+/// Whether a normalized sequence has anything other than A, C, G and T.
+pub(crate) fn is_ambiguous(sequence: &[u8]) -> bool {
+    sequence
+        .iter()
+        .any(|b| !matches!(b, b'A' | b'C' | b'G' | b'T'))
+}
+
+impl SeedIndex {
+    pub(crate) fn new(max_distance: usize) -> SeedIndex {
+        SeedIndex {
+            max_distance,
+            seeds: HashMap::new(),
+            seed_lengths: BTreeSet::new(),
+            unseeded: vec![],
+            ids: vec![],
+        }
+    }
+
+    /// Adds a sequence to the index. Sequences are compared case-insensitively, and U matches T.
+    pub(crate) fn insert(&mut self, id: usize, sequence: &str) {
+        let sequence = normalize(sequence.as_bytes());
+        self.ids.push(id);
+        let pieces = pieces(sequence.len(), self.max_distance);
+        if pieces.is_empty() || is_ambiguous(&sequence) {
+            self.unseeded.push(id);
+            return;
+        }
+        for piece in pieces {
+            self.seed_lengths.insert(piece.len());
+            let ids = self.seeds.entry(sequence[piece].to_vec()).or_default();
+            if ids.last() != Some(&id) {
+                ids.push(id);
+            }
+        }
+    }
+
+    /// The ids of every sequence that shares a piece with `query`, in ascending order.
+    pub(crate) fn candidates(&self, query: &str) -> Vec<usize> {
+        let query = normalize(query.as_bytes());
+        if is_ambiguous(&query) {
+            let mut ids = self.ids.clone();
+            ids.sort_unstable();
+            ids.dedup();
+            return ids;
+        }
+        let mut candidates: BTreeSet<usize> = self.unseeded.iter().copied().collect();
+        for &length in &self.seed_lengths {
+            for window in query.windows(length) {
+                if let Some(ids) = self.seeds.get(window) {
+                    candidates.extend(ids);
+                }
+            }
+        }
+        candidates.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pieces() {
+        assert_eq!(pieces(10, 2), vec![0..4, 4..7, 7..10]);
+        assert_eq!(pieces(3, 2), vec![0..1, 1..2, 2..3]);
+        assert_eq!(pieces(2, 2), vec![]);
+    }
+
+    #[test]
+    fn test_candidates() {
+        let mut index = SeedIndex::new(2);
+        index.insert(0, "ACGTACGTAC");
+        index.insert(1, "TTTTTTTTTT");
+        index.insert(2, "GG");
+        // Three substitutions, one in each piece, so only the short sequence is a candidate.
+        assert_eq!(index.candidates("TCGTTCGTAG"), vec![2]);
+        // One deletion and one substitution.
+        assert_eq!(index.candidates("acgacgtaca"), vec![0, 2]);
+        assert_eq!(index.candidates("TTTTATTTTT"), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_ambiguity_codes() {
+        let mut index = SeedIndex::new(2);
+        index.insert(0, "ACGTACGTAC");
+        index.insert(1, "TTTNTTTNTT");
+        // N matches T, so the sequence with Ns is a candidate even though no piece matches.
+        assert_eq!(index.candidates("TTTTTTTTTT"), vec![1]);
+        // NNN matches ACG, TAC and GTAC in turn.
+        assert_eq!(index.candidates("NNNTACGTAC"), vec![0, 1]);
+    }
+}
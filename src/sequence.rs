@@ -0,0 +1,198 @@
+//! Helpers for working with nucleotide sequences, including IUPAC ambiguity codes.
+
+/// IUPAC codes indexed by a bitmask of the bases they stand for (A = 1, C = 2, G = 4, T = 8).
+const IUPAC_CODES: [u8; 16] = *b"-ACMGRSVTWYHKDBN";
+
+/// The set of bases an IUPAC code stands for, as a bitmask (A = 1, C = 2, G = 4, T = 8).
+/// Anything that is not a nucleotide code gives an empty set.
+pub(crate) fn base_mask(base: u8) -> u8 {
+    match base.to_ascii_uppercase() {
+        b'-' => 0,
+        b'U' => 8,
+        code => IUPAC_CODES
+            .iter()
+            .position(|&c| c == code)
+            .map_or(0, |mask| mask as u8),
+    }
+}
+
+/// The IUPAC code for a set of bases given as a bitmask. An empty set gives `N`.
+pub(crate) fn iupac_code(mask: u8) -> u8 {
+    match mask & 0xf {
+        0 => b'N',
+        mask => IUPAC_CODES[mask as usize],
+    }
+}
+
+/// Whether two nucleotides could be the same base, taking ambiguity codes into account.
+pub(crate) fn bases_match(a: u8, b: u8) -> bool {
+    base_mask(a) & base_mask(b) != 0
+}
+
+/// The number of positions at which two sequences differ. Ambiguity codes match any base they
+/// stand for, and any difference in length counts as that many mismatches.
+pub(crate) fn hamming_distance(a: &str, b: &str) -> usize {
+    let mismatches = a
+        .bytes()
+        .zip(b.bytes())
+        .filter(|&(x, y)| !bases_match(x, y))
+        .count();
+    mismatches + a.len().abs_diff(b.len())
+}
+
+/// The zero-indexed positions at which two sequences differ, as counted by [`hamming_distance`].
+pub(crate) fn mismatch_positions(a: &[u8], b: &[u8]) -> Vec<usize> {
+    (0..a.len().max(b.len()))
+        .filter(|&i| match (a.get(i), b.get(i)) {
+            (Some(&x), Some(&y)) => !bases_match(x, y),
+            _ => true,
+        })
+        .collect()
+}
+
+/// The Levenshtein distance between two sequences, counting substitutions, insertions and
+/// deletions. Ambiguity codes match any base they stand for.
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, &x) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, &y) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(!bases_match(x, y));
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// How similar two sequences are, from 0 to 1: one minus their edit distance divided by the
+/// length of the longer one. Two empty sequences are identical.
+pub(crate) fn identity(a: &str, b: &str) -> f64 {
+    let length = a.len().max(b.len());
+    if length == 0 {
+        return 1.0;
+    }
+    1.0 - edit_distance(a, b) as f64 / length as f64
+}
+
+/// The Shannon entropy of the base composition of a sequence, in bits, from 0 (a single base
+/// repeated) to 2 (all four bases equally common). Ambiguous bases are not counted.
+pub(crate) fn entropy(sequence: &str) -> f64 {
+    let mut counts = [0usize; 4];
+    for base in sequence.bytes() {
+        let mask = base_mask(base);
+        if mask.is_power_of_two() {
+            counts[mask.trailing_zeros() as usize] += 1;
+        }
+    }
+    let total: usize = counts.iter().sum();
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// The complement of a nucleotide, including IUPAC ambiguity codes. Case is preserved, and
+/// anything that is not a nucleotide code is returned unchanged.
+pub(crate) fn complement(base: u8) -> u8 {
+    let mask = base_mask(base);
+    if mask == 0 {
+        return base;
+    }
+    // Reversing the bits swaps A with T and C with G.
+    let code = iupac_code(mask.reverse_bits() >> 4);
+    if base.is_ascii_lowercase() {
+        code.to_ascii_lowercase()
+    } else {
+        code
+    }
+}
+
+/// The reverse complement of a nucleotide sequence.
+pub(crate) fn reverse_complement(sequence: &str) -> String {
+    let complemented = reverse_complement_bytes(sequence.as_bytes());
+    String::from_utf8(complemented).expect("complementing only swaps ASCII characters")
+}
+
+/// The reverse complement of a nucleotide sequence that might not be valid UTF-8, such as one
+/// read from a FASTA file. It is the same length as the sequence, so positions carry over.
+pub(crate) fn reverse_complement_bytes(sequence: &[u8]) -> Vec<u8> {
+    sequence
+        .iter()
+        .rev()
+        .map(|&base| complement(base))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iupac_round_trip() {
+        for mask in 1..16 {
+            assert_eq!(base_mask(iupac_code(mask)), mask);
+        }
+        assert_eq!(base_mask(b'u'), 8);
+        assert_eq!(base_mask(b'X'), 0);
+        assert_eq!(iupac_code(0), b'N');
+    }
+
+    #[test]
+    fn test_reverse_complement() {
+        assert_eq!(reverse_complement("AACGTN"), "NACGTT");
+        assert_eq!(reverse_complement("acgu"), "acgt");
+        assert_eq!(reverse_complement("RYSWKMBDHV"), "BDHVKMWSRY");
+        assert_eq!(reverse_complement("A-C"), "G-T");
+        assert_eq!(reverse_complement(""), "");
+        assert_eq!(reverse_complement_bytes(b"AC\xffGT"), b"AC\xffGT");
+    }
+
+    #[test]
+    fn test_hamming_distance() {
+        assert_eq!(hamming_distance("ACGT", "ACGT"), 0);
+        assert_eq!(hamming_distance("ACGT", "ACCT"), 1);
+        assert_eq!(hamming_distance("ACGT", "ACRT"), 0);
+        assert_eq!(hamming_distance("ACGT", "AC"), 2);
+        assert_eq!(hamming_distance("acgt", "ACGT"), 0);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("ACGT", "ACGT"), 0);
+        assert_eq!(edit_distance("ACGT", "AGT"), 1);
+        assert_eq!(edit_distance("ACGT", "ACGTT"), 1);
+        assert_eq!(edit_distance("ACGT", "TCGA"), 2);
+        assert_eq!(edit_distance("ACGT", "ANGT"), 0);
+        assert_eq!(edit_distance("", "ACG"), 3);
+    }
+
+    #[test]
+    fn test_identity() {
+        assert_eq!(identity("", ""), 1.0);
+        assert_eq!(identity("ACGT", "ACGT"), 1.0);
+        assert_eq!(identity("ACGT", "ACGA"), 0.75);
+        assert_eq!(identity("ACGT", "ACGTACGT"), 0.5);
+    }
+
+    #[test]
+    fn test_entropy() {
+        assert_eq!(entropy(""), 0.0);
+        assert_eq!(entropy("AAAA"), 0.0);
+        assert_eq!(entropy("ATATNN"), 1.0);
+        assert_eq!(entropy("ACGT"), 2.0);
+    }
+
+    #[test]
+    fn test_mismatch_positions() {
+        assert_eq!(mismatch_positions(b"ACGT", b"ACGT"), Vec::<usize>::new());
+        assert_eq!(mismatch_positions(b"ACGTA", b"TCGA"), vec![0, 3, 4]);
+        assert_eq!(mismatch_positions(b"ACNT", b"AGGT"), vec![1]);
+    }
+}
@@ -0,0 +1,167 @@
+//! Predicts the CRISPR-Cas subtype of an array by comparing its consensus repeat to repeats from
+//! systems of known subtype.
+
+use crate::sequence::{identity, reverse_complement};
+use crate::Array;
+use std::io::{self, BufRead};
+
+/// The reference repeats that ship with the crate: one or two well-characterised systems of each
+/// of the common subtypes.
+const BUNDLED_REFERENCES: &str = include_str!("../data/reference_repeats.tsv");
+
+#[derive(Clone, Debug, PartialEq)]
+/// A repeat from a CRISPR-Cas system of known subtype.
+pub struct ReferenceRepeat {
+    /// The subtype, such as `I-E` or `II-A`.
+    pub subtype: String,
+    /// The organism (and strain or locus) the repeat comes from.
+    pub organism: String,
+    /// The repeat sequence.
+    pub repeat: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// How well a repeat matches the closest reference repeat of one subtype.
+pub struct SubtypeMatch<'r> {
+    /// The subtype.
+    pub subtype: &'r str,
+    /// The reference repeat of that subtype that matched best.
+    pub reference: &'r ReferenceRepeat,
+    /// The identity between the repeat and the reference, from 0 to 1: one minus their edit
+    /// distance divided by the length of the longer one.
+    pub score: f64,
+    /// Whether the repeat matched the reverse complement of the reference.
+    pub reverse: bool,
+}
+
+/// Compares repeats against a table of reference repeats of known subtype.
+pub struct SubtypeClassifier {
+    references: Vec<ReferenceRepeat>,
+}
+
+impl SubtypeClassifier {
+    /// A classifier using the reference repeats bundled with the crate.
+    pub fn bundled() -> SubtypeClassifier {
+        SubtypeClassifier::from_tsv(BUNDLED_REFERENCES.as_bytes())
+            .expect("the bundled reference repeats are valid")
+    }
+
+    /// A classifier using reference repeats read from tab-separated values, with the columns
+    /// subtype, organism and repeat. A header line starting with `subtype`, blank lines and lines
+    /// starting with `#` are skipped.
+    pub fn from_tsv<R: BufRead>(reader: R) -> io::Result<SubtypeClassifier> {
+        let mut references = vec![];
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() || line.starts_with('#') || line.starts_with("subtype\t") {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').map(str::trim).collect();
+            match fields[..] {
+                [subtype, organism, repeat] if !repeat.is_empty() => {
+                    references.push(ReferenceRepeat {
+                        subtype: subtype.to_string(),
+                        organism: organism.to_string(),
+                        repeat: repeat.to_ascii_uppercase(),
+                    })
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid reference repeat line: {}", line),
+                    ))
+                }
+            }
+        }
+        Ok(SubtypeClassifier { references })
+    }
+
+    /// The reference repeats.
+    pub fn references(&self) -> &[ReferenceRepeat] {
+        &self.references
+    }
+
+    /// Scores a repeat against every subtype, best first. Each subtype is scored by its closest
+    /// reference repeat in either orientation, since MinCED reports every array on the forward
+    /// strand.
+    pub fn classify(&self, repeat: &str) -> Vec<SubtypeMatch<'_>> {
+        let reverse = reverse_complement(repeat);
+        let mut matches: Vec<SubtypeMatch> = vec![];
+        for reference in &self.references {
+            let forward_score = identity(repeat, &reference.repeat);
+            let reverse_score = identity(&reverse, &reference.repeat);
+            let candidate = SubtypeMatch {
+                subtype: &reference.subtype,
+                reference,
+                score: forward_score.max(reverse_score),
+                reverse: reverse_score > forward_score,
+            };
+            match matches.iter_mut().find(|m| m.subtype == reference.subtype) {
+                Some(existing) if existing.score < candidate.score => *existing = candidate,
+                Some(_) => {}
+                None => matches.push(candidate),
+            }
+        }
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches
+    }
+}
+
+impl<'a> Array<'a> {
+    /// Scores this array's consensus repeat against every subtype, best first (see
+    /// [`SubtypeClassifier::classify`]).
+    pub fn predict_subtype<'r>(&self, classifier: &'r SubtypeClassifier) -> Vec<SubtypeMatch<'r>> {
+        classifier.classify(&self.consensus_repeat())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_bundled() {
+        let classifier = SubtypeClassifier::bundled();
+        assert_eq!(classifier.references().len(), 8);
+
+        // The E. coli repeat with one substitution and the last base missing.
+        let matches = classifier.classify("GAGTTCCCCGCGCCAGCGCGGATAAACC");
+        assert_eq!(matches[0].subtype, "I-E");
+        assert_eq!(matches[0].score, 27.0 / 29.0);
+        assert!(!matches[0].reverse);
+        assert_eq!(matches.len(), 7);
+
+        let matches =
+            classifier.classify(&reverse_complement("GTTTTTGTACTCTCAAGATTTAAGTAACTGTACAAC"));
+        assert_eq!(matches[0].subtype, "II-A");
+        assert_eq!(matches[0].score, 1.0);
+        assert!(matches[0].reverse);
+        assert!(matches[0].reference.organism.contains("thermophilus"));
+    }
+
+    #[test]
+    fn test_from_tsv() {
+        let tsv = "# custom references\nsubtype\torganism\trepeat\nI-C\tExample\tgtcgcactcttcatgggtgcgtggattgaaat\n\n";
+        let classifier = SubtypeClassifier::from_tsv(tsv.as_bytes()).unwrap();
+        assert_eq!(
+            classifier.references(),
+            &[ReferenceRepeat {
+                subtype: "I-C".to_string(),
+                organism: "Example".to_string(),
+                repeat: "GTCGCACTCTTCATGGGTGCGTGGATTGAAAT".to_string(),
+            }]
+        );
+        assert!(SubtypeClassifier::from_tsv("I-C\tExample\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_predict_subtype() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let contigs = parse(&input).unwrap();
+        let classifier = SubtypeClassifier::bundled();
+        let matches = contigs[1].arrays[0].predict_subtype(&classifier);
+        assert_eq!(matches.len(), 7);
+        assert!(matches.windows(2).all(|w| w[0].score >= w[1].score));
+    }
+}
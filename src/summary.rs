@@ -0,0 +1,286 @@
+//! Summary statistics for a whole collection of parsed contigs, for QC reports.
+
+use crate::escape::escape_json;
+use crate::Contig;
+use std::io::{self, Write};
+
+#[derive(Clone, Debug, PartialEq)]
+/// The spread of a set of counts or lengths.
+pub struct Distribution {
+    /// How many values there are.
+    pub count: usize,
+    /// The smallest value, or zero if there are none.
+    pub min: usize,
+    /// The largest value, or zero if there are none.
+    pub max: usize,
+    /// The mean, or zero if there are no values.
+    pub mean: f64,
+    /// The median (the mean of the middle two values if there are an even number), or zero if
+    /// there are no values.
+    pub median: f64,
+}
+
+impl Distribution {
+    /// Summarises some values.
+    pub fn new(mut values: Vec<usize>) -> Distribution {
+        values.sort_unstable();
+        let count = values.len();
+        if count == 0 {
+            return Distribution {
+                count,
+                min: 0,
+                max: 0,
+                mean: 0.0,
+                median: 0.0,
+            };
+        }
+        let median = if count.is_multiple_of(2) {
+            (values[count / 2 - 1] + values[count / 2]) as f64 / 2.0
+        } else {
+            values[count / 2] as f64
+        };
+        Distribution {
+            count,
+            min: values[0],
+            max: values[count - 1],
+            mean: values.iter().sum::<usize>() as f64 / count as f64,
+            median,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Statistics describing the arrays found in a collection of contigs.
+pub struct Summary {
+    /// The number of contigs.
+    pub contigs: usize,
+    /// The number of contigs with at least one array.
+    pub contigs_with_arrays: usize,
+    /// The number of arrays.
+    pub arrays: usize,
+    /// The total length of the contigs ([`Contig::bp`]).
+    pub total_bp: usize,
+    /// The number of bases covered by at least one array.
+    pub crispr_bp: usize,
+    /// The number of arrays on each contig, including contigs with none.
+    pub arrays_per_contig: Distribution,
+    /// The number of repeats in each array.
+    pub repeat_count: Distribution,
+    /// The length of every repeat.
+    pub repeat_length: Distribution,
+    /// The length of every spacer.
+    pub spacer_length: Distribution,
+    /// The length of each array, from the start of its first repeat to the end of its last.
+    pub array_span: Distribution,
+}
+
+impl Summary {
+    /// Computes the statistics for some contigs.
+    pub fn new(contigs: &[Contig]) -> Summary {
+        let mut arrays_per_contig = vec![];
+        let mut repeat_count = vec![];
+        let mut repeat_length = vec![];
+        let mut spacer_length = vec![];
+        let mut array_span = vec![];
+        let mut crispr_bp = 0;
+        for contig in contigs {
+            arrays_per_contig.push(contig.arrays.len());
+            let mut ranges: Vec<(usize, usize)> = vec![];
+            for array in &contig.arrays {
+                repeat_count.push(array.repeat_spacers.len());
+                array_span.push(array.end - array.start);
+                ranges.push((array.start, array.end));
+                for repeat in &array.repeat_spacers {
+                    repeat_length.push(repeat.repeat().len());
+                    if let Some(spacer) = repeat.spacer() {
+                        spacer_length.push(spacer.len());
+                    }
+                }
+            }
+            crispr_bp += covered_bp(ranges);
+        }
+        Summary {
+            contigs: contigs.len(),
+            contigs_with_arrays: arrays_per_contig.iter().filter(|&&n| n > 0).count(),
+            arrays: repeat_count.len(),
+            total_bp: contigs.iter().map(|c| c.bp).sum(),
+            crispr_bp,
+            arrays_per_contig: Distribution::new(arrays_per_contig),
+            repeat_count: Distribution::new(repeat_count),
+            repeat_length: Distribution::new(repeat_length),
+            spacer_length: Distribution::new(spacer_length),
+            array_span: Distribution::new(array_span),
+        }
+    }
+
+    /// The fraction of all bases that are covered by an array, or zero if the contigs have no
+    /// length.
+    pub fn crispr_fraction(&self) -> f64 {
+        if self.total_bp == 0 {
+            0.0
+        } else {
+            self.crispr_bp as f64 / self.total_bp as f64
+        }
+    }
+
+    /// The distributions with their names, in the order they are written.
+    fn distributions(&self) -> [(&'static str, &Distribution); 5] {
+        [
+            ("arrays_per_contig", &self.arrays_per_contig),
+            ("repeat_count", &self.repeat_count),
+            ("repeat_length", &self.repeat_length),
+            ("spacer_length", &self.spacer_length),
+            ("array_span", &self.array_span),
+        ]
+    }
+
+    /// Writes the summary as plain text.
+    pub fn write_text<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "Contigs: {}", self.contigs)?;
+        writeln!(writer, "Contigs with arrays: {}", self.contigs_with_arrays)?;
+        writeln!(writer, "Arrays: {}", self.arrays)?;
+        writeln!(
+            writer,
+            "CRISPR bp: {} of {} ({:.4}%)",
+            self.crispr_bp,
+            self.total_bp,
+            self.crispr_fraction() * 100.0
+        )?;
+        for (name, d) in self.distributions() {
+            writeln!(
+                writer,
+                "{}: n={} min={} max={} mean={:.2} median={:.1}",
+                name, d.count, d.min, d.max, d.mean, d.median
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Writes the summary as a JSON object. Each distribution is an object with the keys
+    /// `count`, `min`, `max`, `mean` and `median`.
+    pub fn write_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write!(
+            writer,
+            "{{\"contigs\":{},\"contigs_with_arrays\":{},\"arrays\":{},\"total_bp\":{},\"crispr_bp\":{},\"crispr_fraction\":{}",
+            self.contigs,
+            self.contigs_with_arrays,
+            self.arrays,
+            self.total_bp,
+            self.crispr_bp,
+            self.crispr_fraction()
+        )?;
+        for (name, d) in self.distributions() {
+            write!(
+                writer,
+                ",\"{}\":{{\"count\":{},\"min\":{},\"max\":{},\"mean\":{},\"median\":{}}}",
+                escape_json(name),
+                d.count,
+                d.min,
+                d.max,
+                d.mean,
+                d.median
+            )?;
+        }
+        writeln!(writer, "}}")
+    }
+
+    /// Writes the summary as two Markdown tables: one of totals and one of distributions.
+    pub fn write_markdown<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "| Statistic | Value |")?;
+        writeln!(writer, "| --- | ---: |")?;
+        writeln!(writer, "| Contigs | {} |", self.contigs)?;
+        writeln!(
+            writer,
+            "| Contigs with arrays | {} |",
+            self.contigs_with_arrays
+        )?;
+        writeln!(writer, "| Arrays | {} |", self.arrays)?;
+        writeln!(writer, "| Total bp | {} |", self.total_bp)?;
+        writeln!(writer, "| CRISPR bp | {} |", self.crispr_bp)?;
+        writeln!(
+            writer,
+            "| CRISPR fraction | {:.4}% |",
+            self.crispr_fraction() * 100.0
+        )?;
+        writeln!(writer)?;
+        writeln!(writer, "| Distribution | n | Min | Max | Mean | Median |")?;
+        writeln!(writer, "| --- | ---: | ---: | ---: | ---: | ---: |")?;
+        for (name, d) in self.distributions() {
+            writeln!(
+                writer,
+                "| {} | {} | {} | {} | {:.2} | {:.1} |",
+                name, d.count, d.min, d.max, d.mean, d.median
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The number of bases covered by at least one of some half-open ranges.
+fn covered_bp(mut ranges: Vec<(usize, usize)>) -> usize {
+    ranges.sort_unstable();
+    let mut covered = 0;
+    let mut reached = 0;
+    for (start, end) in ranges {
+        let start = start.max(reached);
+        if end > start {
+            covered += end - start;
+            reached = end;
+        }
+    }
+    covered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_distribution() {
+        assert_eq!(Distribution::new(vec![]).median, 0.0);
+        let d = Distribution::new(vec![4, 1, 3, 2]);
+        assert_eq!((d.count, d.min, d.max), (4, 1, 4));
+        assert_eq!((d.mean, d.median), (2.5, 2.5));
+        assert_eq!(Distribution::new(vec![5, 1, 2]).median, 2.0);
+    }
+
+    #[test]
+    fn test_covered_bp() {
+        assert_eq!(covered_bp(vec![]), 0);
+        assert_eq!(covered_bp(vec![(10, 20), (15, 30), (40, 50), (42, 45)]), 30);
+    }
+
+    #[test]
+    fn test_summary() {
+        let input = std::fs::read_to_string("examples/minced.txt").unwrap();
+        let contigs = parse(&input).unwrap();
+        let summary = Summary::new(&contigs[..2]);
+        assert_eq!(summary.contigs, 2);
+        assert_eq!(summary.contigs_with_arrays, 2);
+        assert_eq!(summary.arrays, 2);
+        assert_eq!(summary.total_bp, 12280 + 11302);
+        assert_eq!(summary.crispr_bp, 167 + 1410);
+        assert_eq!(summary.array_span.max, 1410);
+        assert_eq!(summary.repeat_count.min, 3);
+        assert_eq!(summary.repeat_length.count, summary.spacer_length.count + 2);
+
+        let mut json = vec![];
+        summary.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.starts_with("{\"contigs\":2,\"contigs_with_arrays\":2,\"arrays\":2,"));
+        assert!(json.contains(",\"array_span\":{\"count\":2,\"min\":167,\"max\":1410,"));
+        assert!(json.ends_with("}}\n"));
+
+        let mut markdown = vec![];
+        summary.write_markdown(&mut markdown).unwrap();
+        let markdown = String::from_utf8(markdown).unwrap();
+        assert!(markdown.contains("| Contigs with arrays | 2 |\n"));
+        assert!(markdown.contains("| array_span | 2 | 167 | 1410 | 788.50 | 788.5 |\n"));
+
+        let mut text = vec![];
+        summary.write_text(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.starts_with("Contigs: 2\nContigs with arrays: 2\nArrays: 2\n"));
+    }
+}
@@ -0,0 +1,257 @@
+//! Checks parsed arrays against the genome they were found in.
+
+use crate::fasta::FastaRecord;
+use crate::sequence::mismatch_positions;
+use crate::{ArrayId, Contig, Repeat};
+use std::collections::HashMap;
+
+/// How far either side of the reported position to look for a sequence that was not found where
+/// it was reported.
+const MAX_SHIFT: usize = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// One part of a repeat-spacer unit.
+pub enum Element {
+    /// The repeat.
+    Repeat,
+    /// The spacer.
+    Spacer,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A disagreement between the MinCED output and the genome.
+pub enum ValidationIssue<'a> {
+    /// No sequence in the genome has the contig's ID.
+    MissingSequence {
+        /// Accession of the contig/genome.
+        accession: &'a str,
+    },
+    /// The genome sequence is not as long as MinCED says.
+    LengthMismatch {
+        /// Accession of the contig/genome.
+        accession: &'a str,
+        /// The length MinCED reported ([`Contig::bp`]).
+        reported: usize,
+        /// The length of the genome sequence.
+        actual: usize,
+    },
+    /// A repeat or spacer runs past the end of the genome sequence.
+    OutOfBounds {
+        /// The array.
+        array: ArrayId<'a>,
+        /// The index of the unit in [`Array::repeat_spacers`](crate::Array::repeat_spacers).
+        index: usize,
+        /// Which part of the unit is out of bounds.
+        element: Element,
+    },
+    /// A repeat or spacer is in the genome, but not quite where it was reported.
+    CoordinateShift {
+        /// The array.
+        array: ArrayId<'a>,
+        /// The index of the unit in [`Array::repeat_spacers`](crate::Array::repeat_spacers).
+        index: usize,
+        /// Which part of the unit is shifted.
+        element: Element,
+        /// How far the sequence actually is from where it was reported, in bp. Positive shifts
+        /// are towards the end of the contig.
+        shift: isize,
+    },
+    /// A repeat or spacer differs from the genome at the reported position.
+    SequenceMismatch {
+        /// The array.
+        array: ArrayId<'a>,
+        /// The index of the unit in [`Array::repeat_spacers`](crate::Array::repeat_spacers).
+        index: usize,
+        /// Which part of the unit differs.
+        element: Element,
+        /// The zero-indexed positions within the repeat or spacer that differ from the genome.
+        mismatches: Vec<usize>,
+    },
+}
+
+/// Checks that every contig is in the genome with the length MinCED reported, and that every
+/// repeat and spacer is found at its coordinates. Contigs are looked up by [`Contig::id`], and
+/// sequences are compared case-insensitively. Returns every issue found, in the order of the
+/// contigs and arrays; an empty list means the output matches the genome.
+pub fn validate<'a>(contigs: &[Contig<'a>], genome: &[FastaRecord]) -> Vec<ValidationIssue<'a>> {
+    let genome: HashMap<&str, &FastaRecord> = genome.iter().map(|r| (r.id(), r)).collect();
+    let mut issues = vec![];
+    for contig in contigs {
+        let sequence = match genome.get(contig.id()) {
+            Some(record) => &record.sequence,
+            None => {
+                issues.push(ValidationIssue::MissingSequence {
+                    accession: contig.accession,
+                });
+                continue;
+            }
+        };
+        if sequence.len() != contig.bp {
+            issues.push(ValidationIssue::LengthMismatch {
+                accession: contig.accession,
+                reported: contig.bp,
+                actual: sequence.len(),
+            });
+        }
+        for array in &contig.arrays {
+            let id = ArrayId::new(contig, array);
+            for (index, unit) in array.repeat_spacers.iter().enumerate() {
+                let elements = match unit {
+                    Repeat::WithSpacer(rs) => vec![
+                        (Element::Repeat, rs.repeat, rs.repeat_start),
+                        (Element::Spacer, rs.spacer, rs.spacer_start),
+                    ],
+                    Repeat::WithoutSpacer(r) => vec![(Element::Repeat, r.repeat, r.start)],
+                };
+                for (element, expected, start) in elements {
+                    if let Some(issue) = check(sequence, expected, start, id, index, element) {
+                        issues.push(issue);
+                    }
+                }
+            }
+        }
+    }
+    issues
+}
+
+/// Checks that `expected` is at `start` in `sequence`. The genome is compared as bytes, so that
+/// anything that is not valid UTF-8 does not shift the coordinates after it.
+fn check<'a>(
+    sequence: &[u8],
+    expected: &str,
+    start: usize,
+    array: ArrayId<'a>,
+    index: usize,
+    element: Element,
+) -> Option<ValidationIssue<'a>> {
+    let expected = expected.as_bytes();
+    let at = |start: usize| {
+        let end = start.checked_add(expected.len())?;
+        sequence.get(start..end)
+    };
+    let matches = |actual: &[u8]| actual.eq_ignore_ascii_case(expected);
+    let actual = match at(start) {
+        Some(actual) if matches(actual) => return None,
+        Some(actual) => actual,
+        None => {
+            return Some(ValidationIssue::OutOfBounds {
+                array,
+                index,
+                element,
+            })
+        }
+    };
+    for distance in 1..=MAX_SHIFT {
+        for shift in [-(distance as isize), distance as isize] {
+            if start
+                .checked_add_signed(shift)
+                .and_then(at)
+                .is_some_and(matches)
+            {
+                return Some(ValidationIssue::CoordinateShift {
+                    array,
+                    index,
+                    element,
+                    shift,
+                });
+            }
+        }
+    }
+    Some(ValidationIssue::SequenceMismatch {
+        array,
+        index,
+        element,
+        mismatches: mismatch_positions(expected, actual),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    const INPUT: &str = "Sequence 'chr1 test genome' (40 bp)
+
+CRISPR 1   Range: 11 - 30
+POSITION	REPEAT	SPACER
+--------	-----	------
+11		GTTTCAAT	ACGT	[ 8, 4 ]
+23		GTTTCAAT
+--------	-----	------
+Repeats: 2	Average Length: 8		Average Length: 4
+
+Time to find repeats: 1 ms
+
+
+";
+
+    fn genome(sequence: &str) -> Vec<FastaRecord> {
+        vec![FastaRecord {
+            header: "chr1".to_string(),
+            sequence: sequence.as_bytes().to_vec(),
+        }]
+    }
+
+    #[test]
+    fn test_valid() {
+        // This also checks that one-based MinCED positions become zero-based coordinates.
+        let contigs = parse(INPUT).unwrap();
+        let genome = genome("AAAAACCCCGGTTTCAATACGTGTTTCAATTGGGGTTTTA");
+        assert!(validate(&contigs, &genome).is_empty());
+
+        // A byte that is not valid UTF-8 does not move the array.
+        let mut genome = genome;
+        genome[0].sequence[0] = 0xff;
+        assert!(validate(&contigs, &genome).is_empty());
+    }
+
+    #[test]
+    fn test_issues() {
+        let array = ArrayId {
+            accession: "chr1 test genome",
+            order: 0,
+        };
+        let contigs = parse(INPUT).unwrap();
+        // The spacer has a mismatch, the last repeat is one base later than reported, and the
+        // genome is one base longer.
+        let genome = genome("AAAAACCCCGGTTTCAATACCTTGTTTCAATGGGGTTTTAA");
+        let expected = vec![
+            ValidationIssue::LengthMismatch {
+                accession: "chr1 test genome",
+                reported: 40,
+                actual: 41,
+            },
+            ValidationIssue::SequenceMismatch {
+                array,
+                index: 0,
+                element: Element::Spacer,
+                mismatches: vec![2],
+            },
+            ValidationIssue::CoordinateShift {
+                array,
+                index: 1,
+                element: Element::Repeat,
+                shift: 1,
+            },
+        ];
+        assert_eq!(validate(&contigs, &genome), expected);
+
+        let genome = self::genome("AAAAACCCCGGTTTCAATACGTGTTTCA");
+        let issues = validate(&contigs, &genome);
+        assert_eq!(
+            issues.last(),
+            Some(&ValidationIssue::OutOfBounds {
+                array,
+                index: 1,
+                element: Element::Repeat
+            })
+        );
+
+        assert_eq!(
+            validate(&contigs, &[]),
+            vec![ValidationIssue::MissingSequence {
+                accession: "chr1 test genome"
+            }]
+        );
+    }
+}